@@ -1,7 +1,6 @@
 const nearAPI = require('near-api-js');
 const { keyStores } = nearAPI;
 const db = require('./middle_db');
-const crypto = require('crypto');
 
 class NFTApi {
     constructor(config) {
@@ -101,8 +100,13 @@ class NFTApi {
         }
     }
 
-    async mintNFT(channelId, proof, receiverId) {
+    // Callers generate requestId once per mint (e.g. with crypto.randomUUID()) and pass the same
+    // id on every retry, so the contract returns the token it already minted
+    async mintNFT(channelId, proof, receiverId, requestId) {
         console.log(`Minting NFT for channel: ${channelId}`);
+        if (!requestId) {
+            throw new Error('requestId is required so retries are not minted twice');
+        }
         
         try {
             // Calculate required storage deposit
@@ -114,6 +118,7 @@ class NFTApi {
                     channel_id: channelId,
                     proof: proof ? proof.map(p => Array.from(p)) : null,
                     receiver_id: receiverId,
+                    options: { request_id: requestId },
                 },
                 300000000000000, // gas
                 storageCost // attached deposit for storage
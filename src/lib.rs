@@ -1,15 +1,61 @@
+
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{UnorderedMap, UnorderedSet, LookupMap};
-use near_sdk::{env, near_bindgen, AccountId, PanicOnDefault, BorshStorageKey};
+use near_sdk::collections::{UnorderedMap, UnorderedSet, LookupMap, LookupSet};
+use near_sdk::{env, near_bindgen, AccountId, PanicOnDefault, BorshStorageKey, CurveType, PublicKey};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::json_types::Base64VecU8;
 use near_sdk::json_types::U128;
 use near_sdk::serde_json;
 use std::clone::Clone;
-use near_sdk::{Promise, assert_one_yocto};
-use near_sdk::NearToken;
+use std::collections::BTreeMap;
+use near_sdk::{Allowance, Promise, PromiseError, PromiseOrValue, assert_one_yocto, ext_contract};
+use near_sdk::{NearToken, Gas};
 
 pub const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1);
+// Gas kept back by paged jobs so the call can finish writing state
+pub const PAGED_JOB_GAS_RESERVE: Gas = Gas::from_tgas(20);
+pub const MAX_REVENUE_SPLITS: usize = 10;
+pub const MAX_VOUCHER_SIGNERS: usize = 5;
+pub const MAX_VOUCHER_QUANTITY: u64 = 20;
+// Used to estimate sponsored mints before the pool has paid for one
+pub const ESTIMATED_MINT_STORAGE_BYTES: u64 = 500;
+// Gas fees a claim key may spend, kept back from each key's deposit
+pub const CLAIM_KEY_ALLOWANCE: NearToken = NearToken::from_millinear(50);
+pub const CLAIM_METHOD_NAMES: &str = "claim,create_account_and_claim";
+pub const GAS_FOR_CREATE_ACCOUNT: Gas = Gas::from_tgas(30);
+pub const GAS_FOR_RESOLVE_CLAIM: Gas = Gas::from_tgas(30);
+// Pending mints can be claimed for 30 days unless the channel sets another window
+pub const DEFAULT_PENDING_WINDOW: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+// Request ids passed to `nft_mint` are remembered for a day
+pub const MINT_REQUEST_WINDOW: u64 = 24 * 60 * 60 * 1_000_000_000;
+pub const MAX_REQUEST_ID_LEN: usize = 64;
+// Expired request ids each `nft_mint` removes from the front of the expiry queue
+pub const MINT_REQUEST_EVICTIONS: u64 = 5;
+// Unrevealed mint commitments expire after about three hours
+pub const MINT_COMMITMENT_TTL_BLOCKS: u64 = 10_000;
+// Blocks between committing to a pending mint claim and presenting its secret
+pub const PENDING_CLAIM_DELAY_BLOCKS: u64 = 1;
+// Mints per `nft_mint` event in airdrops, keeping each log well under the size limit
+pub const AIRDROP_EVENT_BATCH: usize = 50;
+// Tokens per `nft_metadata_update` event when a template change or reveal is announced
+pub const METADATA_UPDATE_EVENT_BATCH: u64 = 50;
+pub const MAX_SNAPSHOT_QUANTITY: u64 = 10;
+pub const MAX_RESERVED_RANGES: usize = 10;
+pub const MAX_CHANNEL_TIERS: usize = 10;
+pub const MAX_TOKEN_ATTRIBUTES: usize = 20;
+pub const MAX_TOKEN_ATTRIBUTE_LEN: usize = 256;
+pub const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+pub const GAS_FOR_RESOLVE_FT_WITHDRAW: Gas = Gas::from_tgas(10);
+
+#[ext_contract(ext_ft)]
+pub trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+#[ext_contract(ext_account_creator)]
+pub trait AccountCreator {
+    fn create_account(&mut self, new_account_id: AccountId, new_public_key: PublicKey) -> bool;
+}
 
 #[derive(BorshStorageKey, BorshSerialize)]
 pub enum StorageKey {
@@ -19,6 +65,60 @@ pub enum StorageKey {
     Owners,
     OwnerTokens { account_id: AccountId },
     ChannelIndex,
+    Roles,
+    RoleMembers { role: ContractRole },
+    ChannelRoles,
+    ChannelRoleMembers { channel_id: String, role: ChannelRole },
+    MinterAllowances,
+    ChannelMintedTokens { channel_id: String },
+    ChannelProceeds,
+    ChannelFtProceeds,
+    AuctionPayments,
+    PayeeBalances,
+    ReferralCounts,
+    StoragePools,
+    SponsoredMints,
+    VoucherNonces,
+    ClaimKeys,
+    PendingMints,
+    ChannelPendingIndex,
+    ChannelPendingMints { channel_id: String },
+    RecycledPendingTokens,
+    MintRequests,
+    MintCommitments,
+    ChannelRandomSwaps { channel_id: String },
+    TokenOwners,
+    SnapshotAirdrops,
+    SnapshotAirdropHolders,
+    TokenTiers,
+    TokenMintedAt,
+    TokenMetadataOverrides,
+    TokenStates,
+    MetadataUpdateJobs,
+    RandomMintReceivers,
+    MintRequestQueue,
+    ChannelGenerations,
+    ChannelFtProceedsTokens,
+    AuctionRounds,
+}
+
+// Contract-wide roles. Admins can do anything the owner can except grant or revoke Admin.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ContractRole {
+    Admin,
+    ChannelCreator,
+    Pauser,
+}
+
+// Roles scoped to a single channel
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ChannelRole {
+    ChannelManager,
+    Minter,
+    MetadataEditor,
+    StateUpdater,
 }
 
 #[near_bindgen]
@@ -30,6 +130,44 @@ pub struct Contract {
     pub metadata: NFTContractMetadata,
     pub owners: LookupMap<AccountId, UnorderedSet<TokenId>>,
     pub channel_index: UnorderedMap<u16, String>,
+    pub roles: LookupMap<ContractRole, UnorderedSet<AccountId>>,
+    pub channel_roles: LookupMap<(String, ChannelRole), UnorderedSet<AccountId>>,
+    pub minter_allowances: LookupMap<(String, AccountId), MinterAllowance>,
+    pub paused: bool,
+    pub channel_proceeds: LookupMap<String, NearToken>,
+    pub total_proceeds: NearToken,
+    pub channel_ft_proceeds: LookupMap<(String, AccountId), u128>,
+    pub auction_payments: LookupMap<(u64, AccountId), AuctionPayment>,
+    pub payee_balances: LookupMap<AccountId, NearToken>,
+    pub total_payee_balances: NearToken,
+    pub referral_counts: LookupMap<(String, AccountId), u64>,
+    pub storage_pools: LookupMap<String, StoragePool>,
+    pub total_storage_pools: NearToken,
+    pub sponsored_mints: LookupMap<(String, AccountId), u64>,
+    pub voucher_nonces: LookupSet<(String, u64)>,
+    pub claim_keys: LookupMap<PublicKey, ClaimKey>,
+    pub pending_mints: LookupMap<Vec<u8>, PendingMint>,
+    pub channel_pending_mints: LookupMap<String, UnorderedSet<Vec<u8>>>,
+    pub recycled_pending_tokens: LookupMap<String, Vec<TokenId>>,
+    pub mint_requests: LookupMap<(AccountId, String), MintRequest>,
+    pub mint_commitments: LookupMap<Vec<u8>, MintCommitment>,
+    pub token_owners: LookupMap<TokenId, AccountId>,
+    pub snapshot_airdrops: LookupMap<u64, SnapshotAirdrop>,
+    pub snapshot_airdrop_holders: LookupSet<(u64, AccountId)>,
+    pub next_snapshot_airdrop_id: u64,
+    pub token_tiers: LookupMap<TokenId, String>,
+    pub token_minted_at: LookupMap<TokenId, u64>,
+    pub token_metadata_overrides: LookupMap<TokenId, TokenMetadataOverride>,
+    pub token_states: LookupMap<TokenId, TokenState>,
+    pub metadata_update_jobs: LookupMap<String, MetadataUpdateJob>,
+    pub random_mint_receivers: LookupSet<(String, AccountId)>,
+    pub mint_request_queue: LookupMap<u64, (AccountId, String)>,
+    pub mint_request_queue_start: u64,
+    pub mint_request_queue_end: u64,
+    pub channel_generations: LookupMap<String, u64>,
+    pub channel_ft_proceeds_tokens: LookupMap<String, Vec<AccountId>>,
+    pub auction_rounds: LookupMap<u64, SettledAuction>,
+    pub next_auction_round: u64,
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
@@ -40,6 +178,30 @@ pub struct Channel {
     pub total_supply: u64,
     pub next_token_number: u64,
     pub metadata: ChannelMetadata,
+    pub paused: bool,
+    pub archived: bool,
+    pub metadata_frozen: bool,
+    pub root_frozen: bool,
+    pub frozen: bool,
+    pub mint_price: NearToken,
+    pub ft_price: Option<FtPrice>,
+    pub pricing_curve: Option<PricingCurve>,
+    pub auction: Option<AuctionState>,
+    pub revenue_splits: Vec<RevenueSplit>,
+    pub splits_frozen: bool,
+    pub referral_bps: u16,
+    pub voucher_signers: Vec<PublicKey>,
+    pub pending_window: u64,
+    pub burn_expired_pending: bool,
+    pub commit_reveal_blocks: Option<u64>,
+    pub rarity_tiers: Vec<RarityTier>,
+    pub placeholder: Option<ChannelMetadata>,
+    pub provenance_hash: Option<Base64VecU8>,
+    pub reveal: Option<Reveal>,
+    pub reserved_ranges: Vec<ReservedRange>,
+    pub tiers: Vec<ChannelTier>,
+    // Sparse Fisher-Yates state for random assignment, (tier index, position) -> token offset
+    pub random_swaps: UnorderedMap<(u32, u64), u64>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -50,6 +212,28 @@ pub struct ChannelView {
     pub total_supply: u64,
     pub next_token_number: u64,
     pub metadata: ChannelMetadata,
+    pub paused: bool,
+    pub archived: bool,
+    pub metadata_frozen: bool,
+    pub root_frozen: bool,
+    pub frozen: bool,
+    pub mint_price: NearToken,
+    pub ft_price: Option<FtPrice>,
+    pub pricing_curve: Option<PricingCurve>,
+    pub auction: Option<AuctionState>,
+    pub revenue_splits: Vec<RevenueSplit>,
+    pub splits_frozen: bool,
+    pub referral_bps: u16,
+    pub voucher_signers: Vec<PublicKey>,
+    pub pending_window: u64,
+    pub burn_expired_pending: bool,
+    pub commit_reveal_blocks: Option<u64>,
+    pub rarity_tiers: Vec<RarityTier>,
+    pub placeholder: Option<ChannelMetadata>,
+    pub provenance_hash: Option<Base64VecU8>,
+    pub reveal: Option<Reveal>,
+    pub reserved_ranges: Vec<ReservedRange>,
+    pub tiers: Vec<ChannelTier>,
 }
 
 impl From<Channel> for ChannelView {
@@ -60,6 +244,28 @@ impl From<Channel> for ChannelView {
             total_supply: channel.total_supply,
             next_token_number: channel.next_token_number,
             metadata: channel.metadata,
+            paused: channel.paused,
+            archived: channel.archived,
+            metadata_frozen: channel.metadata_frozen,
+            root_frozen: channel.root_frozen,
+            frozen: channel.frozen,
+            mint_price: channel.mint_price,
+            ft_price: channel.ft_price,
+            pricing_curve: channel.pricing_curve,
+            auction: channel.auction,
+            revenue_splits: channel.revenue_splits,
+            splits_frozen: channel.splits_frozen,
+            referral_bps: channel.referral_bps,
+            voucher_signers: channel.voucher_signers,
+            pending_window: channel.pending_window,
+            burn_expired_pending: channel.burn_expired_pending,
+            commit_reveal_blocks: channel.commit_reveal_blocks,
+            rarity_tiers: channel.rarity_tiers,
+            placeholder: channel.placeholder,
+            provenance_hash: channel.provenance_hash,
+            reveal: channel.reveal,
+            reserved_ranges: channel.reserved_ranges,
+            tiers: channel.tiers,
         }
     }
 }
@@ -75,6 +281,35 @@ pub struct ChannelMetadata {
     pub reference_hash: Option<Base64VecU8>,
 }
 
+// One-off metadata for a single token, set fields replace the rendered channel template
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenMetadataOverride {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub media: Option<String>,
+    pub animation_url: Option<String>,
+    pub reference: Option<String>,
+    pub reference_hash: Option<Base64VecU8>,
+    pub extra: Option<String>,
+}
+
+// Mutable state of an evolving token, rendered by `{level}` and `{attribute.<key>}` and into `extra`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenState {
+    pub level: u32,
+    pub attributes: BTreeMap<String, String>,
+}
+
+// Announces `nft_metadata_update` for the channel's first `total` minted tokens after a template change or reveal
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MetadataUpdateJob {
+    pub total: u64,
+    pub cursor: u64,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct TokenMetadata {
@@ -84,6 +319,7 @@ pub struct TokenMetadata {
     pub animation_url: Option<String>,
     pub reference: String,
     pub reference_hash: Option<Base64VecU8>,
+    pub extra: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -93,6 +329,266 @@ pub struct JsonToken {
     pub metadata: TokenMetadata,
 }
 
+// Quota for a partner account minting into a channel without proofs
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MinterAllowance {
+    pub remaining: u64,
+    pub expires_at: Option<u64>,    // Block timestamp in nanoseconds
+    pub receivers: Option<Vec<AccountId>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseStatus {
+    pub contract_paused: bool,
+    pub channel_paused: Option<bool>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtPrice {
+    pub token_id: AccountId,    // NEP-141 contract the price is paid in
+    pub amount: U128,
+}
+
+// Replaces the fixed mint price. Bonding curves are driven by `total_supply`,
+// the Dutch auction decays linearly from `start_price` to `floor_price` over `duration` nanoseconds.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PricingCurve {
+    Linear { base_price: NearToken, increment: NearToken },
+    Exponential { base_price: NearToken, growth_bps: u32 },
+    DutchAuction { start_price: NearToken, floor_price: NearToken, start_time: u64, duration: u64 },
+}
+
+// Dutch auction payments are held in escrow until the clearing price is settled. Rounds are
+// numbered across all channels, settling moves the escrowed rebates to the round's record.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionState {
+    pub round: u64,
+    pub escrow: NearToken,
+    pub quantity: u64,
+    pub last_price: Option<NearToken>,
+    pub clearing_price: Option<NearToken>,
+}
+
+// Rebates of a settled auction round still owed to its buyers
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SettledAuction {
+    pub channel_id: String,
+    pub clearing_price: NearToken,
+    pub escrow: NearToken,
+}
+
+// Share of each paid mint in basis points, the remainder stays in the channel proceeds
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RevenueSplit {
+    pub account_id: AccountId,
+    pub bps: u16,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionPayment {
+    pub round: u64,
+    pub total_paid: NearToken,
+    pub quantity: u64,
+}
+
+// Payload signed by a channel's voucher signer
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintVoucher {
+    pub contract_id: AccountId,     // Keeps the voucher from being replayed on another deployment
+    pub channel_id: String,
+    pub receiver_id: AccountId,
+    pub token_number: Option<u64>,
+    pub quantity: Option<u64>,
+    pub nonce: u64,
+    pub expires_at: u64,    // Block timestamp in nanoseconds
+}
+
+// Function-call key on the contract that can claim one token of a channel
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimKey {
+    pub channel_id: String,
+    pub deposit: NearToken,
+    pub depositor_id: AccountId,
+    pub generation: u64,
+}
+
+// Token held in escrow by the contract until someone presents the secret behind `secret_hash`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingMint {
+    pub secret_hash: Base64VecU8,
+    pub channel_id: String,
+    pub token_id: TokenId,
+    pub minter_id: AccountId,
+    pub expires_at: u64,
+}
+
+// Token minted for a caller's request id, used to answer retries of the same request
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct MintRequest {
+    pub token_id: TokenId,
+    pub expires_at: u64,
+}
+
+// Hash of a future `reveal_mint`, the deposit pays for its storage and is refunded on reveal or expiry
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintCommitment {
+    pub committer_id: AccountId,
+    pub block_height: u64,
+    pub deposit: NearToken,
+}
+
+// Tier requested at channel creation, its supply is fixed from then on
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RarityTierConfig {
+    pub name: String,
+    pub weight: u32,
+    pub supply: u64,
+}
+
+// Tier of a randomized channel, owning token numbers `first_number..first_number + supply`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RarityTier {
+    pub name: String,
+    pub weight: u32,
+    pub supply: u64,
+    pub remaining: u64,
+    pub first_number: u64,
+}
+
+// Shift applied at reveal, token `n` shows final item `(n - 1 + offset) % size + 1`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Reveal {
+    pub offset: u64,
+    pub size: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SnapshotReward {
+    PerHolder,
+    PerToken,
+}
+
+// Job minting `quantity` target tokens per holder or held token of the source channel, walking
+// the source's first `total` minted token numbers
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SnapshotAirdrop {
+    pub source_channel_id: String,
+    pub target_channel_id: String,
+    pub reward: SnapshotReward,
+    pub quantity: u64,
+    pub total: u64,
+    pub cursor: u64,
+    pub minted_at_cursor: u64,
+    pub rewarded: u64,
+    pub minted: u64,
+}
+
+// Inclusive range of token numbers that only channel managers can mint
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReservedRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+// Named tier of a channel with its own allowlist root, media, supply and flat price
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChannelTier {
+    pub name: String,
+    pub merkle_root: Vec<u8>,
+    pub media: String,
+    pub animation_url: Option<String>,
+    pub supply: u64,
+    pub price: NearToken,
+    pub minted: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChannelTierConfig {
+    pub name: String,
+    pub merkle_root: Vec<u8>,
+    pub media: String,
+    pub animation_url: Option<String>,
+    pub supply: u64,
+    pub price: NearToken,
+}
+
+// Optional arguments of `nft_mint`
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintOptions {
+    pub referrer_id: Option<AccountId>,
+    pub request_id: Option<String>,
+    pub token_number: Option<u64>,
+    pub tier: Option<String>,
+}
+
+// What vouches for a mint before `internal_mint` checks roles, allowances and proofs
+#[derive(Clone, Copy, PartialEq)]
+pub enum MintAuthorization {
+    // A Merkle proof passed with the call, refused on commit-reveal channels
+    Proof,
+    // A Merkle proof revealed by `reveal_mint`
+    Revealed,
+    // Vouchers, claim keys and airdrops, which need no proof
+    Trusted,
+}
+
+// Message passed through `ft_transfer_call` to mint
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtMintMessage {
+    pub channel_id: String,
+    pub receiver_id: Option<AccountId>,
+    pub proof: Option<Vec<Vec<u8>>>,
+}
+
+// Prepaid NEAR that covers mint storage for callers attaching no deposit
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StoragePool {
+    pub balance: NearToken,
+    pub per_account_limit: Option<u64>,
+    pub last_mint_cost: Option<NearToken>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StoragePoolView {
+    pub balance: NearToken,
+    pub per_account_limit: Option<u64>,
+    pub estimated_remaining_mints: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TreasuryView {
+    pub total_proceeds: NearToken,
+    pub payee_balances: NearToken,
+    pub storage_pools: NearToken,
+    pub storage_locked: NearToken,
+    pub balance: NearToken,
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct NFTContractMetadata {
@@ -112,7 +608,7 @@ pub type TokenId = String; // Format: "{channel_id}:{token_number}"
 impl Contract {
     #[init]
     pub fn new(owner_id: AccountId) -> Self {
-        let contract = Self {
+        Self {
             owner_id: owner_id.clone(),
             channels: UnorderedMap::new(StorageKey::Channels),
             minted_tokens: UnorderedSet::new(StorageKey::MintedTokens),
@@ -127,20 +623,94 @@ impl Contract {
             },
             owners: LookupMap::new(StorageKey::Owners),
             channel_index: UnorderedMap::new(StorageKey::ChannelIndex),
-        };
-        contract
+            roles: LookupMap::new(StorageKey::Roles),
+            channel_roles: LookupMap::new(StorageKey::ChannelRoles),
+            minter_allowances: LookupMap::new(StorageKey::MinterAllowances),
+            paused: false,
+            channel_proceeds: LookupMap::new(StorageKey::ChannelProceeds),
+            total_proceeds: NearToken::from_yoctonear(0),
+            channel_ft_proceeds: LookupMap::new(StorageKey::ChannelFtProceeds),
+            auction_payments: LookupMap::new(StorageKey::AuctionPayments),
+            payee_balances: LookupMap::new(StorageKey::PayeeBalances),
+            total_payee_balances: NearToken::from_yoctonear(0),
+            referral_counts: LookupMap::new(StorageKey::ReferralCounts),
+            storage_pools: LookupMap::new(StorageKey::StoragePools),
+            total_storage_pools: NearToken::from_yoctonear(0),
+            sponsored_mints: LookupMap::new(StorageKey::SponsoredMints),
+            voucher_nonces: LookupSet::new(StorageKey::VoucherNonces),
+            claim_keys: LookupMap::new(StorageKey::ClaimKeys),
+            pending_mints: LookupMap::new(StorageKey::PendingMints),
+            channel_pending_mints: LookupMap::new(StorageKey::ChannelPendingIndex),
+            recycled_pending_tokens: LookupMap::new(StorageKey::RecycledPendingTokens),
+            mint_requests: LookupMap::new(StorageKey::MintRequests),
+            mint_commitments: LookupMap::new(StorageKey::MintCommitments),
+            token_owners: LookupMap::new(StorageKey::TokenOwners),
+            snapshot_airdrops: LookupMap::new(StorageKey::SnapshotAirdrops),
+            snapshot_airdrop_holders: LookupSet::new(StorageKey::SnapshotAirdropHolders),
+            next_snapshot_airdrop_id: 0,
+            token_tiers: LookupMap::new(StorageKey::TokenTiers),
+            token_minted_at: LookupMap::new(StorageKey::TokenMintedAt),
+            token_metadata_overrides: LookupMap::new(StorageKey::TokenMetadataOverrides),
+            token_states: LookupMap::new(StorageKey::TokenStates),
+            metadata_update_jobs: LookupMap::new(StorageKey::MetadataUpdateJobs),
+            random_mint_receivers: LookupSet::new(StorageKey::RandomMintReceivers),
+            mint_request_queue: LookupMap::new(StorageKey::MintRequestQueue),
+            mint_request_queue_start: 0,
+            mint_request_queue_end: 0,
+            channel_generations: LookupMap::new(StorageKey::ChannelGenerations),
+            channel_ft_proceeds_tokens: LookupMap::new(StorageKey::ChannelFtProceedsTokens),
+            auction_rounds: LookupMap::new(StorageKey::AuctionRounds),
+            next_auction_round: 0,
+        }
     }
 
     pub fn nft_metadata(&self) -> NFTContractMetadata {
         self.metadata.clone()
     }
 
-    pub fn create_channel(&mut self, channel_id: String, merkle_root: Vec<u8>, metadata: ChannelMetadata) {
-        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only the owner can create channels");
+    // Channels created with rarity tiers hand out a random unminted token number of a weighted
+    // random tier instead of the next token number, and are capped at the tiers' total supply
+    pub fn create_channel(
+        &mut self,
+        channel_id: String,
+        merkle_root: Vec<u8>,
+        metadata: ChannelMetadata,
+        rarity_tiers: Option<Vec<RarityTierConfig>>,
+    ) {
+        let creator_id = env::predecessor_account_id();
+        assert!(
+            self.is_admin(&creator_id) || self.account_has_role(ContractRole::ChannelCreator, &creator_id),
+            "Only the owner, an admin or a channel creator can create channels"
+        );
+        assert!(self.channels.get(&channel_id).is_none(), "Channel already exists");
+        assert!(!channel_id.contains(':'), "Channel ids cannot contain ':'");
+        validate_channel_metadata(&metadata);
+
+        let mut next_first_number: u64 = 1;
+        let rarity_tiers: Vec<RarityTier> = rarity_tiers
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tier| {
+                assert!(tier.weight > 0 && tier.supply > 0, "Rarity tiers need a positive weight and supply");
+                let first_number = next_first_number;
+                next_first_number = next_first_number.checked_add(tier.supply).expect("Rarity tier supply overflow");
+                RarityTier {
+                    name: tier.name,
+                    weight: tier.weight,
+                    supply: tier.supply,
+                    remaining: tier.supply,
+                    first_number,
+                }
+            })
+            .collect();
+        let total_possible = if rarity_tiers.is_empty() { u64::MAX } else { next_first_number - 1 };
+
         let channel = Channel {
             merkle_root,
-            total_possible: u64::MAX,    // Set to maximum possible value
-            minted_tokens: UnorderedSet::new(b"m"),
+            total_possible,    // Unlimited unless the channel has rarity tiers
+            minted_tokens: UnorderedSet::new(StorageKey::ChannelMintedTokens {
+                channel_id: channel_id.clone(),
+            }),
             total_supply: 0,
             next_token_number: 1,
             metadata: ChannelMetadata {
@@ -151,9 +721,39 @@ impl Contract {
                 reference: metadata.reference.clone(),
                 reference_hash: metadata.reference_hash.clone(),
             },
+            paused: false,
+            archived: false,
+            metadata_frozen: false,
+            root_frozen: false,
+            frozen: false,
+            mint_price: NearToken::from_yoctonear(0),
+            ft_price: None,
+            pricing_curve: None,
+            auction: None,
+            revenue_splits: Vec::new(),
+            splits_frozen: false,
+            referral_bps: 0,
+            voucher_signers: Vec::new(),
+            pending_window: DEFAULT_PENDING_WINDOW,
+            burn_expired_pending: false,
+            commit_reveal_blocks: None,
+            rarity_tiers,
+            placeholder: None,
+            provenance_hash: None,
+            reveal: None,
+            reserved_ranges: Vec::new(),
+            tiers: Vec::new(),
+            random_swaps: UnorderedMap::new(StorageKey::ChannelRandomSwaps {
+                channel_id: channel_id.clone(),
+            }),
         };
         self.channels.insert(&channel_id, &channel);
 
+        // Channel creators manage the channels they create
+        if !self.is_admin(&creator_id) {
+            self.add_channel_role_member(&channel_id, ChannelRole::ChannelManager, &creator_id);
+        }
+
         // Log the creation of the new channel
         env::log_str(&format!(
             "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"create_series\",\"data\":{{\"series_id\":\"{}\",\"metadata\":{}}}}}",
@@ -163,19 +763,33 @@ impl Contract {
     }
 
     pub fn update_channel(&mut self, channel_id: String, merkle_root: Option<Vec<u8>>, metadata: Option<ChannelMetadata>) {
-        // Only the contract owner can update channels
-        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only the owner can update channels");
+        // Root changes need a channel manager, metadata changes a manager or metadata editor
+        let caller = env::predecessor_account_id();
+        if merkle_root.is_some() {
+            assert!(self.can_manage_channel(&channel_id, &caller), "Only the owner, an admin or a channel manager can update the Merkle root");
+        }
+        if metadata.is_some() {
+            assert!(
+                self.can_manage_channel(&channel_id, &caller)
+                    || self.account_has_channel_role(&channel_id, ChannelRole::MetadataEditor, &caller),
+                "Only the owner, an admin, a channel manager or a metadata editor can update channel metadata"
+            );
+        }
     
         // Get the existing channel
         let mut channel = self.channels.get(&channel_id).expect("Channel not found");
     
         // Update merkle root if provided
         if let Some(new_merkle_root) = merkle_root {
+            assert!(!channel.root_frozen, "Channel Merkle root is frozen");
             channel.merkle_root = new_merkle_root;
         }
     
         // Update metadata if provided
+        let metadata_changed = metadata.is_some();
         if let Some(new_metadata) = metadata {
+            assert!(!channel.metadata_frozen, "Channel metadata is frozen");
+            validate_channel_metadata(&new_metadata);
             channel.metadata = ChannelMetadata {
                 title_template: new_metadata.title_template,
                 description_template: new_metadata.description_template,
@@ -195,6 +809,223 @@ impl Contract {
             channel_id,
             serde_json::to_string(&channel.metadata).unwrap()
         ));
+
+        // Restart the announcement for every token minted so far, a first page runs right away
+        if metadata_changed {
+            self.metadata_update_jobs.insert(&channel_id, &MetadataUpdateJob {
+                total: channel.minted_tokens.len(),
+                cursor: 0,
+            });
+            self.internal_run_metadata_update_job(&channel_id, &channel);
+        }
+    }
+
+    // Continues announcing a template change across calls, returns whether the job is done
+    pub fn run_metadata_update_job(&mut self, channel_id: String) -> bool {
+        let caller = env::predecessor_account_id();
+        assert!(
+            self.can_manage_channel(&channel_id, &caller)
+                || self.account_has_channel_role(&channel_id, ChannelRole::MetadataEditor, &caller),
+            "Only the owner, an admin, a channel manager or a metadata editor can run metadata updates"
+        );
+        let channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(self.metadata_update_jobs.get(&channel_id).is_some(), "No metadata update job for this channel");
+        self.internal_run_metadata_update_job(&channel_id, &channel)
+    }
+
+    pub fn get_metadata_update_job(&self, channel_id: String) -> Option<MetadataUpdateJob> {
+        self.metadata_update_jobs.get(&channel_id)
+    }
+
+    // Emits `nft_metadata_update` events of up to `METADATA_UPDATE_EVENT_BATCH` tokens until the gas runs low
+    fn internal_run_metadata_update_job(&mut self, channel_id: &String, channel: &Channel) -> bool {
+        let Some(mut job) = self.metadata_update_jobs.get(channel_id) else {
+            return true;
+        };
+        while job.cursor < job.total {
+            if env::prepaid_gas().saturating_sub(env::used_gas()) < PAGED_JOB_GAS_RESERVE {
+                break;
+            }
+            let end = (job.cursor + METADATA_UPDATE_EVENT_BATCH).min(job.total);
+            // Burned tokens stay in the channel's set but no longer exist, so they are skipped
+            let token_ids: Vec<TokenId> = (job.cursor..end)
+                .filter_map(|index| channel.minted_tokens.as_vector().get(index))
+                .map(|token_number| format!("{}:{}", channel_id, token_number))
+                .filter(|token_id| self.minted_tokens.contains(token_id))
+                .collect();
+            if !token_ids.is_empty() {
+                self.log_metadata_update_event(channel_id, &token_ids);
+            }
+            job.cursor = end;
+        }
+
+        let done = job.cursor >= job.total;
+        if done {
+            self.metadata_update_jobs.remove(channel_id);
+        } else {
+            self.metadata_update_jobs.insert(channel_id, &job);
+        }
+        done
+    }
+
+    // Turns the channel into a mystery drop before its first mint, `provenance_hash` being the
+    // sha256 of the Borsh-serialized final `ChannelMetadata` that `reveal_channel` will supply
+    pub fn set_mystery_drop(&mut self, channel_id: String, placeholder: ChannelMetadata, provenance_hash: Base64VecU8) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can set up a mystery drop"
+        );
+        assert_eq!(provenance_hash.0.len(), 32, "Provenance hash must be 32 bytes");
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.frozen, "Channel is frozen");
+        assert!(!channel.metadata_frozen, "Channel metadata is frozen");
+        assert_eq!(channel.next_token_number, 1, "Mystery drops must be set up before the first mint");
+        validate_channel_metadata(&placeholder);
+        channel.placeholder = Some(placeholder);
+        channel.provenance_hash = Some(provenance_hash);
+        self.channels.insert(&channel_id, &channel);
+    }
+
+    // One-time reveal of a mystery drop. The metadata must match the provenance hash, so the
+    // reveal goes through even once the channel's metadata is frozen.
+    pub fn reveal_channel(&mut self, channel_id: String, metadata: ChannelMetadata) -> Reveal {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can reveal a channel"
+        );
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        let provenance_hash = channel.provenance_hash.clone().expect("Channel is not a mystery drop");
+        assert!(channel.reveal.is_none(), "Channel already revealed");
+        assert_eq!(
+            env::sha256(borsh::to_vec(&metadata).unwrap()),
+            provenance_hash.0,
+            "Metadata does not match the provenance hash"
+        );
+        validate_channel_metadata(&metadata);
+
+        // Capped channels shift over their whole supply, unlimited ones over what was minted so far
+        let size = if channel.total_possible != u64::MAX {
+            channel.total_possible
+        } else {
+            channel.next_token_number - 1
+        }.max(1);
+        let seed = env::random_seed();
+        let reveal = Reveal {
+            offset: u64::from_le_bytes(seed[0..8].try_into().unwrap()) % size,
+            size,
+        };
+        channel.metadata = metadata;
+        channel.placeholder = None;
+        channel.reveal = Some(reveal.clone());
+        self.channels.insert(&channel_id, &channel);
+
+        // Announces every minted token like a template change, `run_metadata_update_job` pages the rest
+        self.metadata_update_jobs.insert(&channel_id, &MetadataUpdateJob {
+            total: channel.minted_tokens.len(),
+            cursor: 0,
+        });
+        self.internal_run_metadata_update_job(&channel_id, &channel);
+
+        reveal
+    }
+
+    // Sets the fields of `overrides` that are present on one token, replacing any earlier override
+    pub fn set_token_metadata_override(&mut self, token_id: TokenId, overrides: TokenMetadataOverride) {
+        let channel_id = self.get_token_to_override(&token_id);
+        assert!(
+            overrides.title.is_some()
+                || overrides.description.is_some()
+                || overrides.media.is_some()
+                || overrides.animation_url.is_some()
+                || overrides.reference.is_some()
+                || overrides.reference_hash.is_some()
+                || overrides.extra.is_some(),
+            "Override has no fields, remove it instead"
+        );
+        self.token_metadata_overrides.insert(&token_id, &overrides);
+        self.log_metadata_update_event(&channel_id, &[token_id]);
+    }
+
+    pub fn remove_token_metadata_override(&mut self, token_id: TokenId) {
+        let channel_id = self.get_token_to_override(&token_id);
+        assert!(
+            self.token_metadata_overrides.remove(&token_id).is_some(),
+            "Token has no metadata override"
+        );
+        self.log_metadata_update_event(&channel_id, &[token_id]);
+    }
+
+    pub fn get_token_metadata_override(&self, token_id: TokenId) -> Option<TokenMetadataOverride> {
+        self.token_metadata_overrides.get(&token_id)
+    }
+
+    fn get_token_to_override(&self, token_id: &TokenId) -> String {
+        let (channel_id, _) = token_id.split_once(':').expect("Invalid token id");
+        let channel_id = channel_id.to_string();
+        let caller = env::predecessor_account_id();
+        assert!(
+            self.can_manage_channel(&channel_id, &caller)
+                || self.account_has_channel_role(&channel_id, ChannelRole::MetadataEditor, &caller),
+            "Only the owner, an admin, a channel manager or a metadata editor can override token metadata"
+        );
+        assert!(self.minted_tokens.contains(token_id), "Token not found");
+        let channel = self.channels.get(&channel_id).expect("Channel not found");
+        self.assert_not_paused(&channel);
+        assert!(!channel.metadata_frozen, "Channel metadata is frozen");
+        channel_id
+    }
+
+    fn log_metadata_update_event(&self, channel_id: &str, token_ids: &[TokenId]) {
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.1.0\",\"event\":\"nft_metadata_update\",\"data\":[{{\"series_id\":\"{}\",\"token_ids\":{}}}]}}",
+            channel_id,
+            serde_json::to_string(token_ids).unwrap()
+        ));
+    }
+
+    // Levels a token up or down and sets or removes its attributes, `None` attribute values remove the key
+    pub fn update_token_state(&mut self, token_id: TokenId, level: Option<u32>, attributes: Option<Vec<(String, Option<String>)>>) {
+        let (channel_id, _) = token_id.split_once(':').expect("Invalid token id");
+        let channel_id = channel_id.to_string();
+        let caller = env::predecessor_account_id();
+        assert!(
+            self.can_manage_channel(&channel_id, &caller)
+                || self.account_has_channel_role(&channel_id, ChannelRole::StateUpdater, &caller),
+            "Only the owner, an admin, a channel manager or a state updater can update token state"
+        );
+        assert!(self.minted_tokens.contains(&token_id), "Token not found");
+        let channel = self.channels.get(&channel_id).expect("Channel not found");
+        self.assert_not_paused(&channel);
+        assert!(!channel.frozen, "Channel is frozen");
+        assert!(!channel.metadata_frozen, "Channel metadata is frozen");
+
+        let mut state = self.token_states.get(&token_id).unwrap_or_default();
+        if let Some(level) = level {
+            state.level = level;
+        }
+        for (key, value) in attributes.unwrap_or_default() {
+            assert!(
+                !key.is_empty() && key.len() <= MAX_TOKEN_ATTRIBUTE_LEN && !key.contains(['{', '}']),
+                "Invalid attribute key"
+            );
+            match value {
+                Some(value) => {
+                    assert!(value.len() <= MAX_TOKEN_ATTRIBUTE_LEN, "Attribute value is too long");
+                    state.attributes.insert(key, value);
+                }
+                None => {
+                    state.attributes.remove(&key);
+                }
+            }
+        }
+        assert!(state.attributes.len() <= MAX_TOKEN_ATTRIBUTES, "Too many token attributes");
+
+        self.token_states.insert(&token_id, &state);
+        self.log_metadata_update_event(&channel_id, &[token_id]);
+    }
+
+    pub fn get_token_state(&self, token_id: TokenId) -> Option<TokenState> {
+        self.token_states.get(&token_id)
     }
 
     pub fn nft_token(&self, token_id: String) -> Option<JsonToken> {
@@ -207,15 +1038,87 @@ impl Contract {
         let token_number: u64 = parts[1].parse().ok()?;
     
         let channel = self.channels.get(&channel_id.to_string())?;
+        // Rarity tiers of randomized channels only carry a name
+        let tier_name = self.token_tiers.get(&token_id);
+        let tier = tier_name
+            .as_ref()
+            .and_then(|name| channel.tiers.iter().find(|tier| tier.name == *name));
+        let state = self.token_states.get(&token_id);
+        let context = TemplateContext {
+            number: token_number,
+            item: match &channel.reveal {
+                Some(reveal) if token_number <= reveal.size => (token_number - 1 + reveal.offset) % reveal.size + 1,
+                _ => token_number,
+            },
+            channel: channel_id,
+            tier: tier_name.as_deref(),
+            max_supply: match tier {
+                Some(tier) => Some(tier.supply),
+                None => Some(channel.total_possible).filter(|total_possible| *total_possible != u64::MAX),
+            },
+            minted_at: self.token_minted_at.get(&token_id),
+            owner: self.token_owners.get(&token_id),
+            state: state.as_ref(),
+        };
+        let extra = state.as_ref().map(|state| serde_json::to_string(state).unwrap());
     
-        let token_metadata = TokenMetadata {
-            title: channel.metadata.title_template.replace("{}", &token_number.to_string()),
-            description: channel.metadata.description_template.clone(),
-            media: channel.metadata.media.clone(),
-            animation_url: channel.metadata.animation_url.clone(),
-            reference: format!("{}/{}", channel.metadata.reference, token_number),
-            reference_hash: channel.metadata.reference_hash.clone(),
+        // Unrevealed mystery drops show the placeholder for every token
+        let mut token_metadata = match (&channel.placeholder, &channel.reveal) {
+            (Some(placeholder), None) => TokenMetadata {
+                title: render_template(&placeholder.title_template, &context),
+                description: render_template(&placeholder.description_template, &context),
+                media: render_template(&placeholder.media, &context),
+                animation_url: placeholder.animation_url.clone(),
+                reference: render_template(&placeholder.reference, &context),
+                reference_hash: placeholder.reference_hash.clone(),
+                extra,
+            },
+            _ => {
+                // Plain references keep getting the item number appended
+                let reference = if channel.metadata.reference.contains('{') {
+                    render_template(&channel.metadata.reference, &context)
+                } else {
+                    format!("{}/{}", channel.metadata.reference, context.item)
+                };
+                // Tiered tokens take their tier's media
+                TokenMetadata {
+                    title: render_template(&channel.metadata.title_template, &context),
+                    description: render_template(&channel.metadata.description_template, &context),
+                    media: render_template(tier.map_or(&channel.metadata.media, |tier| &tier.media), &context),
+                    animation_url: match tier {
+                        Some(tier) => tier.animation_url.clone(),
+                        None => channel.metadata.animation_url.clone(),
+                    },
+                    reference,
+                    reference_hash: channel.metadata.reference_hash.clone(),
+                    extra,
+                }
+            }
         };
+
+        if let Some(overrides) = self.token_metadata_overrides.get(&token_id) {
+            if let Some(title) = overrides.title {
+                token_metadata.title = title;
+            }
+            if let Some(description) = overrides.description {
+                token_metadata.description = description;
+            }
+            if let Some(media) = overrides.media {
+                token_metadata.media = media;
+            }
+            if overrides.animation_url.is_some() {
+                token_metadata.animation_url = overrides.animation_url;
+            }
+            if let Some(reference) = overrides.reference {
+                token_metadata.reference = reference;
+            }
+            if overrides.reference_hash.is_some() {
+                token_metadata.reference_hash = overrides.reference_hash;
+            }
+            if overrides.extra.is_some() {
+                token_metadata.extra = overrides.extra;
+            }
+        }
     
         Some(JsonToken {
             token_id,
@@ -230,68 +1133,630 @@ impl Contract {
         channel_id: String,
         proof: Option<Vec<Vec<u8>>>,
         receiver_id: AccountId,
+        options: Option<MintOptions>,
     ) -> TokenId {
-        let initial_storage = env::storage_usage();
-        
-        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
-        let token_number = channel.next_token_number;
-        
-        // Remove total_possible check since we want unlimited minting
-        
-        // Verify proof for non-first tokens
-        if token_number > 1 {
-            let proof = proof.expect("Proof required for minting");
-            let token_id = format!("{}:{}", channel_id, token_number);
+        let options = options.unwrap_or_default();
+        let minter_id = env::predecessor_account_id();
+        if let Some(request_id) = &options.request_id {
+            assert!(request_id.len() <= MAX_REQUEST_ID_LEN, "Request id is too long");
+            // A retried request gets the token minted the first time and its deposit back
+            if let Some(token_id) = self.get_mint_request(minter_id.clone(), request_id.clone()) {
+                let refund = env::attached_deposit();
+                if !refund.is_zero() {
+                    let _ = Promise::new(minter_id).transfer(refund);
+                }
+                return token_id;
+            }
+        }
+        if let Some(referrer_id) = &options.referrer_id {
             assert!(
-                self.verify_merkle_proof(&channel.merkle_root, &token_id, &proof),
-                "Invalid proof"
+                *referrer_id != minter_id && *referrer_id != receiver_id,
+                "Self-referral is not allowed"
             );
         }
 
-        let token_id = format!("{}:{}", channel_id, token_number);
-        
-        // Update states
-        channel.minted_tokens.insert(&token_number);
-        channel.next_token_number += 1;
-        channel.total_supply += 1;
-        self.channels.insert(&channel_id, &channel);
-        
-        self.minted_tokens.insert(&token_id);
-        let mut owner_tokens = self.owners
-            .get(&receiver_id)
-            .unwrap_or_else(|| UnorderedSet::new(StorageKey::OwnerTokens {
-                account_id: receiver_id.clone(),
-            }));
-        owner_tokens.insert(&token_id);
-        self.owners.insert(&receiver_id, &owner_tokens);
+        let initial_storage = env::storage_usage();
+        let (token_ids, price) = self.internal_mint_priced(&channel_id, proof, &receiver_id, &options, 1, MintAuthorization::Proof);
+        if let Some(referrer_id) = &options.referrer_id {
+            let key = (self.channel_scope(&channel_id), referrer_id.clone());
+            let count = self.referral_counts.get(&key).unwrap_or(0);
+            self.referral_counts.insert(&key, &(count + 1));
+        }
 
-        // Verify sufficient deposit
-        let required_storage = env::storage_usage() - initial_storage;
-        let required_cost = env::storage_byte_cost().saturating_mul(required_storage as u128);
-        
-        assert!(
-            env::attached_deposit() >= required_cost,
-            "Must attach {} yoctoNEAR to cover storage",
-            required_cost,
-        );
+        let memo = options.referrer_id.map(|referrer_id| format!("referrer_id:{}", referrer_id));
+        self.log_mint_event(&receiver_id, &token_ids, memo.as_deref());
 
-        // Emit NEP-171 event
-        env::log_str(&format!(
-            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"nft_mint\",\"data\":[{{\"owner_id\":\"{}\",\"token_ids\":[\"{}\"]}}]}}",
-            receiver_id,
-            token_id
-        ));
+        let token_id = token_ids.into_iter().next().unwrap();
+        if let Some(request_id) = options.request_id {
+            let key = (minter_id, request_id);
+            self.mint_requests.insert(&key, &MintRequest {
+                token_id: token_id.clone(),
+                expires_at: env::block_timestamp().saturating_add(MINT_REQUEST_WINDOW),
+            });
+            self.mint_request_queue.insert(&self.mint_request_queue_end, &key);
+            self.mint_request_queue_end += 1;
+        }
+        self.evict_mint_requests();
+        self.charge_mint_deposit(&channel_id, initial_storage, price);
 
         token_id
     }
 
-
-    pub fn is_minted(&self, token_id: TokenId) -> bool {
-        self.minted_tokens.contains(&token_id)
+    // Request ids expire in the order they were recorded, so the oldest are dropped from the
+    // front of the queue. A key reused after expiry holds the queue until its new entry expires.
+    fn evict_mint_requests(&mut self) {
+        let now = env::block_timestamp();
+        for _ in 0..MINT_REQUEST_EVICTIONS {
+            if self.mint_request_queue_start == self.mint_request_queue_end {
+                break;
+            }
+            let key = self.mint_request_queue.get(&self.mint_request_queue_start).unwrap();
+            if self.mint_requests.get(&key).is_some_and(|request| request.expires_at > now) {
+                break;
+            }
+            self.mint_requests.remove(&key);
+            self.mint_request_queue.remove(&self.mint_request_queue_start);
+            self.mint_request_queue_start += 1;
+        }
     }
 
-    pub fn get_next_token_number(&self, channel_id: String) -> Option<u64> {
-        self.channels.get(&channel_id).map(|channel| channel.next_token_number)
+    // First step of commit-reveal minting, `commitment` is the sha256 of the Borsh-serialized
+    // `(channel_id, receiver_id, proof, referrer_id, token_number, tier, salt)` that will be
+    // revealed, or of the `(secret, receiver_id)` of a later `claim_pending`
+    #[payable]
+    pub fn commit_mint(&mut self, commitment: Base64VecU8) {
+        // The commitment hides its channel, so only the contract-wide pause applies until the reveal
+        assert!(!self.paused, "Contract is paused");
+        assert_eq!(commitment.0.len(), 32, "Commitment must be 32 bytes");
+        assert!(self.mint_commitments.get(&commitment.0).is_none(), "Commitment already exists");
+        let initial_storage = env::storage_usage();
+
+        let deposit = env::attached_deposit();
+        self.mint_commitments.insert(&commitment.0, &MintCommitment {
+            committer_id: env::predecessor_account_id(),
+            block_height: env::block_height(),
+            deposit,
+        });
+
+        let storage_cost = env::storage_byte_cost().saturating_mul((env::storage_usage() - initial_storage) as u128);
+        assert!(
+            deposit >= storage_cost,
+            "Must attach {} yoctoNEAR to cover the commitment storage",
+            storage_cost.as_yoctonear()
+        );
+    }
+
+    // Mints once the channel's commit-reveal delay has passed since the matching commitment,
+    // the attached deposit pays for the mint as in `nft_mint`. The referrer, token number and
+    // tier are part of the commitment, request ids only apply to `nft_mint`.
+    #[payable]
+    pub fn reveal_mint(
+        &mut self,
+        channel_id: String,
+        receiver_id: AccountId,
+        proof: Option<Vec<Vec<u8>>>,
+        options: Option<MintOptions>,
+        salt: String,
+    ) -> TokenId {
+        let channel = self.channels.get(&channel_id).expect("Channel not found");
+        let min_blocks = channel.commit_reveal_blocks.expect("Channel does not use commit-reveal minting");
+        let options = MintOptions {
+            request_id: None,
+            ..options.unwrap_or_default()
+        };
+
+        let commitment = env::sha256(borsh::to_vec(&(
+            &channel_id,
+            &receiver_id,
+            &proof,
+            &options.referrer_id,
+            &options.token_number,
+            &options.tier,
+            &salt,
+        )).unwrap());
+        let commit = self.mint_commitments.get(&commitment).expect("No matching commitment");
+        assert_eq!(commit.committer_id, env::predecessor_account_id(), "Only the committer can reveal");
+        let age = env::block_height().saturating_sub(commit.block_height);
+        assert!(age >= min_blocks, "Commitment is too recent to reveal");
+        assert!(age < MINT_COMMITMENT_TTL_BLOCKS, "Commitment expired");
+        self.mint_commitments.remove(&commitment);
+
+        let token_ids = self.internal_mint_with_deposit(&channel_id, proof, &receiver_id, &options, 1, MintAuthorization::Revealed);
+        self.log_mint_event(&receiver_id, &token_ids, None);
+        if !commit.deposit.is_zero() {
+            let _ = Promise::new(commit.committer_id).transfer(commit.deposit);
+        }
+
+        token_ids.into_iter().next().unwrap()
+    }
+
+    // Anyone can clear a stale commitment, its deposit goes back to the committer
+    pub fn expire_mint_commitment(&mut self, commitment: Base64VecU8) {
+        let commit = self.mint_commitments.get(&commitment.0).expect("No matching commitment");
+        assert!(
+            env::block_height().saturating_sub(commit.block_height) >= MINT_COMMITMENT_TTL_BLOCKS,
+            "Commitment has not expired"
+        );
+        self.mint_commitments.remove(&commitment.0);
+        if !commit.deposit.is_zero() {
+            let _ = Promise::new(commit.committer_id).transfer(commit.deposit);
+        }
+    }
+
+    // Requires `min_blocks` between commit and reveal, `None` switches back to plain `nft_mint`
+    pub fn set_commit_reveal(&mut self, channel_id: String, min_blocks: Option<u64>) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can set commit-reveal minting"
+        );
+        if let Some(min_blocks) = min_blocks {
+            assert!(min_blocks < MINT_COMMITMENT_TTL_BLOCKS, "Reveal delay exceeds the commitment lifetime");
+        }
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.frozen, "Channel is frozen");
+        channel.commit_reveal_blocks = min_blocks;
+        self.channels.insert(&channel_id, &channel);
+    }
+
+    pub fn get_mint_commitment(&self, commitment: Base64VecU8) -> Option<MintCommitment> {
+        self.mint_commitments.get(&commitment.0)
+    }
+
+    // Token minted for a request id in the last `MINT_REQUEST_WINDOW`
+    pub fn get_mint_request(&self, account_id: AccountId, request_id: String) -> Option<TokenId> {
+        self.mint_requests
+            .get(&(account_id, request_id))
+            .filter(|request| env::block_timestamp() < request.expires_at)
+            .map(|request| request.token_id)
+    }
+
+    // Mints into escrow for a recipient who only has the secret behind `secret_hash` (sha256),
+    // reusing a token recycled from an expired pending mint when the channel has one. Recycled
+    // tokens need the same proof, price and deposit as a fresh mint of their number.
+    #[payable]
+    pub fn nft_mint_pending(
+        &mut self,
+        channel_id: String,
+        secret_hash: Base64VecU8,
+        proof: Option<Vec<Vec<u8>>>,
+    ) -> TokenId {
+        assert_eq!(secret_hash.0.len(), 32, "Secret hash must be 32 bytes");
+        assert!(self.pending_mints.get(&secret_hash.0).is_none(), "Secret hash already used");
+        let channel = self.channels.get(&channel_id).expect("Channel not found");
+        self.assert_not_paused(&channel);
+        assert!(!channel.archived, "Channel is archived");
+        let escrow_id = env::current_account_id();
+        let minter_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+
+        let mut recycled = self.recycled_pending_tokens.get(&channel_id).unwrap_or_default();
+        let (token_id, price) = match recycled.pop() {
+            Some(token_id) => {
+                if recycled.is_empty() {
+                    self.recycled_pending_tokens.remove(&channel_id);
+                } else {
+                    self.recycled_pending_tokens.insert(&channel_id, &recycled);
+                }
+
+                assert!(channel.ft_price.is_none(), "Channel is priced in a fungible token");
+                let tier = self.token_tiers
+                    .get(&token_id)
+                    .and_then(|name| channel.tiers.iter().find(|tier| tier.name == name).cloned());
+                if !self.is_trusted_minter(&channel_id, &channel, &minter_id, &escrow_id, false, MintAuthorization::Proof) {
+                    let leaf = if channel.rarity_tiers.is_empty() {
+                        token_id.clone()
+                    } else {
+                        format!("{}:{}", channel_id, escrow_id)
+                    };
+                    let merkle_root = tier.as_ref().map_or(&channel.merkle_root, |tier| &tier.merkle_root);
+                    self.assert_valid_proof(merkle_root, &leaf, proof);
+                }
+                let price = match &tier {
+                    Some(tier) => tier.price,
+                    None => self.quote_mint_price(&channel, 1),
+                };
+                let channel = self.channels.get(&channel_id).unwrap();
+                self.record_mint_payment(&channel_id, channel, price, &minter_id, None);
+                (token_id, price)
+            }
+            None => {
+                let (token_ids, price) = self.internal_mint_priced(&channel_id, proof, &escrow_id, &MintOptions::default(), 1, MintAuthorization::Proof);
+                self.log_mint_event(&escrow_id, &token_ids, Some("pending"));
+                (token_ids.into_iter().next().unwrap(), price)
+            }
+        };
+
+        let pending = PendingMint {
+            secret_hash: secret_hash.clone(),
+            channel_id: channel_id.clone(),
+            token_id: token_id.clone(),
+            minter_id,
+            expires_at: env::block_timestamp().saturating_add(channel.pending_window),
+        };
+        self.pending_mints.insert(&secret_hash.0, &pending);
+        let mut channel_pending = self.channel_pending_mints
+            .get(&channel_id)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKey::ChannelPendingMints {
+                channel_id: channel_id.clone(),
+            }));
+        channel_pending.insert(&secret_hash.0);
+        self.channel_pending_mints.insert(&channel_id, &channel_pending);
+        self.charge_mint_deposit(&channel_id, initial_storage, price);
+
+        token_id
+    }
+
+    // Moves a pending token out of escrow to the receiver. The caller must have committed to the
+    // `(secret, receiver_id)` pair with `commit_mint` at least `PENDING_CLAIM_DELAY_BLOCKS` earlier,
+    // so a secret seen in a claim can't be replayed for another receiver.
+    pub fn claim_pending(&mut self, secret: String, receiver_id: AccountId) -> TokenId {
+        let secret_hash = env::sha256(secret.as_bytes());
+        let pending = self.pending_mints.get(&secret_hash).expect("No pending mint for this secret");
+        assert!(env::block_timestamp() < pending.expires_at, "Pending mint expired");
+        self.assert_token_not_paused(&pending.token_id);
+
+        let commitment = env::sha256(borsh::to_vec(&(&secret, &receiver_id)).unwrap());
+        let commit = self.mint_commitments.get(&commitment).expect("No matching commitment");
+        assert_eq!(commit.committer_id, env::predecessor_account_id(), "Only the committer can reveal");
+        let age = env::block_height().saturating_sub(commit.block_height);
+        assert!(age >= PENDING_CLAIM_DELAY_BLOCKS, "Commitment is too recent to reveal");
+        assert!(age < MINT_COMMITMENT_TTL_BLOCKS, "Commitment expired");
+        self.mint_commitments.remove(&commitment);
+
+        self.remove_pending_mint(&pending);
+        self.internal_transfer(&env::current_account_id(), &receiver_id, &pending.token_id, Some("claim_pending".to_string()));
+        if !commit.deposit.is_zero() {
+            let _ = Promise::new(commit.committer_id).transfer(commit.deposit);
+        }
+
+        pending.token_id
+    }
+
+    // Anyone can expire an unclaimed pending mint, its token is burned or kept in escrow
+    // for the channel's next pending mint depending on the channel's policy
+    pub fn expire_pending_mint(&mut self, secret_hash: Base64VecU8) {
+        let pending = self.pending_mints.get(&secret_hash.0).expect("No pending mint for this secret");
+        assert!(env::block_timestamp() >= pending.expires_at, "Pending mint has not expired");
+        let channel = self.channels.get(&pending.channel_id).expect("Channel not found");
+        self.assert_not_paused(&channel);
+        self.remove_pending_mint(&pending);
+
+        if channel.burn_expired_pending {
+            self.internal_burn(&env::current_account_id(), &pending.token_id);
+        } else {
+            let mut recycled = self.recycled_pending_tokens.get(&pending.channel_id).unwrap_or_default();
+            recycled.push(pending.token_id.clone());
+            self.recycled_pending_tokens.insert(&pending.channel_id, &recycled);
+        }
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"expire_pending_mint\",\"data\":{{\"series_id\":\"{}\",\"token_id\":\"{}\",\"burned\":{}}}}}",
+            pending.channel_id,
+            pending.token_id,
+            channel.burn_expired_pending
+        ));
+    }
+
+    pub fn set_pending_mint_policy(&mut self, channel_id: String, pending_window: u64, burn_expired: bool) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can set the pending mint policy"
+        );
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.frozen, "Channel is frozen");
+        channel.pending_window = pending_window;
+        channel.burn_expired_pending = burn_expired;
+        self.channels.insert(&channel_id, &channel);
+    }
+
+    pub fn get_pending_mint(&self, secret_hash: Base64VecU8) -> Option<PendingMint> {
+        self.pending_mints.get(&secret_hash.0)
+    }
+
+    pub fn get_pending_mints(
+        &self,
+        channel_id: String,
+        from_index: Option<U128>,
+        limit: Option<u64>
+    ) -> Vec<PendingMint> {
+        let start = u128::from(from_index.unwrap_or(U128(0))) as usize;
+        let limit = limit.unwrap_or(50) as usize;
+
+        self.channel_pending_mints
+            .get(&channel_id)
+            .map(|hashes| {
+                hashes.iter()
+                    .skip(start)
+                    .take(limit)
+                    .filter_map(|secret_hash| self.pending_mints.get(&secret_hash))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn remove_pending_mint(&mut self, pending: &PendingMint) {
+        self.pending_mints.remove(&pending.secret_hash.0);
+        if let Some(mut channel_pending) = self.channel_pending_mints.get(&pending.channel_id) {
+            channel_pending.remove(&pending.secret_hash.0);
+            if channel_pending.is_empty() {
+                self.channel_pending_mints.remove(&pending.channel_id);
+            } else {
+                self.channel_pending_mints.insert(&pending.channel_id, &channel_pending);
+            }
+        }
+    }
+
+    // Mints from a voucher signed by one of the channel's signer keys over the exact `payload` bytes,
+    // `payload` being a JSON `MintVoucher`. Vouchers replace the Merkle proof.
+    #[payable]
+    pub fn nft_mint_with_voucher(&mut self, payload: String, signature: Base64VecU8) -> Vec<TokenId> {
+        let voucher: MintVoucher = serde_json::from_str(&payload).expect("Invalid voucher");
+        let channel = self.channels.get(&voucher.channel_id).expect("Channel not found");
+
+        let signature: [u8; 64] = signature.0.try_into().expect("Invalid voucher signature");
+        assert!(
+            channel.voucher_signers.iter().any(|public_key| {
+                let key: [u8; 32] = public_key.as_bytes()[1..].try_into().unwrap();
+                env::ed25519_verify(&signature, payload.as_bytes(), &key)
+            }),
+            "Invalid voucher signature"
+        );
+        assert_eq!(voucher.contract_id, env::current_account_id(), "Voucher is for another contract");
+        assert!(env::block_timestamp() < voucher.expires_at, "Voucher expired");
+
+        // The minter's deposit also covers the nonce's storage
+        let initial_storage = env::storage_usage();
+        assert!(
+            self.voucher_nonces.insert(&(self.channel_scope(&voucher.channel_id), voucher.nonce)),
+            "Voucher already used"
+        );
+
+        let quantity = voucher.quantity.unwrap_or(1);
+        assert!(quantity > 0 && quantity <= MAX_VOUCHER_QUANTITY, "Invalid voucher quantity");
+        let options = MintOptions {
+            token_number: voucher.token_number,
+            ..Default::default()
+        };
+        let (token_ids, price) = self.internal_mint_priced(&voucher.channel_id, None, &voucher.receiver_id, &options, quantity, MintAuthorization::Trusted);
+        self.charge_mint_deposit(&voucher.channel_id, initial_storage, price);
+        self.log_mint_event(&voucher.receiver_id, &token_ids, None);
+
+        token_ids
+    }
+
+    pub fn add_voucher_signer(&mut self, channel_id: String, public_key: PublicKey) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can manage voucher signers"
+        );
+        assert!(public_key.curve_type() == CurveType::ED25519, "Voucher signers must be ed25519 keys");
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.voucher_signers.contains(&public_key), "Voucher signer already registered");
+        assert!(channel.voucher_signers.len() < MAX_VOUCHER_SIGNERS, "Too many voucher signers");
+        channel.voucher_signers.push(public_key.clone());
+        self.channels.insert(&channel_id, &channel);
+        self.log_voucher_signer_event("add_voucher_signer", &channel_id, &public_key);
+    }
+
+    pub fn remove_voucher_signer(&mut self, channel_id: String, public_key: PublicKey) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can manage voucher signers"
+        );
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        let index = channel.voucher_signers
+            .iter()
+            .position(|key| *key == public_key)
+            .expect("Voucher signer not found");
+        channel.voucher_signers.remove(index);
+        self.channels.insert(&channel_id, &channel);
+        self.log_voucher_signer_event("remove_voucher_signer", &channel_id, &public_key);
+    }
+
+    pub fn is_voucher_nonce_used(&self, channel_id: String, nonce: u64) -> bool {
+        self.voucher_nonces.contains(&(self.channel_scope(&channel_id), nonce))
+    }
+
+    fn log_voucher_signer_event(&self, event: &str, channel_id: &str, public_key: &PublicKey) {
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"{}\",\"data\":{{\"series_id\":\"{}\",\"public_key\":{}}}}}",
+            event,
+            channel_id,
+            serde_json::to_string(public_key).unwrap()
+        ));
+    }
+
+    // Registers claim keys for QR-code drops, the attached deposit is split evenly between the keys
+    // and pays for the key allowance, the claim's storage and, for new accounts, their initial balance
+    #[payable]
+    pub fn add_claim_keys(&mut self, channel_id: String, public_keys: Vec<PublicKey>) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can add claim keys"
+        );
+        let channel = self.channels.get(&channel_id).expect("Channel not found");
+        self.assert_not_paused(&channel);
+        assert!(!public_keys.is_empty(), "No claim keys provided");
+        let initial_storage = env::storage_usage();
+        let generation = self.channel_generations.get(&channel_id).unwrap_or(0);
+
+        for public_key in &public_keys {
+            assert!(self.claim_keys.get(public_key).is_none(), "Claim key already registered");
+            self.claim_keys.insert(public_key, &ClaimKey {
+                channel_id: channel_id.clone(),
+                deposit: NearToken::from_yoctonear(0),
+                depositor_id: env::predecessor_account_id(),
+                generation,
+            });
+        }
+
+        let storage_cost = env::storage_byte_cost().saturating_mul((env::storage_usage() - initial_storage) as u128);
+        let deposit = env::attached_deposit()
+            .checked_sub(storage_cost)
+            .expect("Deposit does not cover claim key storage")
+            .as_yoctonear() / public_keys.len() as u128;
+        let deposit = NearToken::from_yoctonear(deposit);
+        let min_deposit = CLAIM_KEY_ALLOWANCE
+            .saturating_add(env::storage_byte_cost().saturating_mul(ESTIMATED_MINT_STORAGE_BYTES as u128));
+        assert!(
+            deposit >= min_deposit,
+            "Must attach at least {} yoctoNEAR per claim key",
+            min_deposit.as_yoctonear()
+        );
+
+        let current_account_id = env::current_account_id();
+        let mut promise = Promise::new(current_account_id.clone());
+        for public_key in public_keys {
+            self.claim_keys.insert(&public_key, &ClaimKey {
+                channel_id: channel_id.clone(),
+                deposit,
+                depositor_id: env::predecessor_account_id(),
+                generation,
+            });
+            promise = promise.add_access_key_allowance(
+                public_key,
+                Allowance::limited(CLAIM_KEY_ALLOWANCE).unwrap(),
+                current_account_id.clone(),
+                CLAIM_METHOD_NAMES.to_string(),
+            );
+        }
+    }
+
+    // Revokes an unused claim key, its deposit goes back to whoever funded it
+    pub fn remove_claim_key(&mut self, public_key: PublicKey) -> Promise {
+        let claim = self.claim_keys.get(&public_key).expect("Claim key not found");
+        assert!(
+            self.can_manage_channel(&claim.channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can remove claim keys"
+        );
+        self.claim_keys.remove(&public_key);
+
+        Promise::new(env::current_account_id())
+            .delete_key(public_key)
+            .then(Promise::new(claim.depositor_id).transfer(claim.deposit.saturating_sub(CLAIM_KEY_ALLOWANCE)))
+    }
+
+    // Signed by a claim key, mints into the key's channel and deletes the key
+    pub fn claim(&mut self, receiver_id: AccountId) -> TokenId {
+        let (public_key, claim) = self.take_claim_key();
+        let initial_storage = env::storage_usage();
+
+        let (token_id, _) = self.internal_mint(&claim.channel_id, None, &env::current_account_id(), &receiver_id, &MintOptions::default(), MintAuthorization::Trusted);
+        self.log_mint_event(&receiver_id, std::slice::from_ref(&token_id), None);
+
+        // The unused deposit goes back to whoever funded the key
+        let storage_cost = env::storage_byte_cost()
+            .saturating_mul(env::storage_usage().saturating_sub(initial_storage) as u128);
+        let refund = claim.deposit.saturating_sub(CLAIM_KEY_ALLOWANCE).saturating_sub(storage_cost);
+        let _ = Promise::new(env::current_account_id()).delete_key(public_key);
+        if !refund.is_zero() {
+            let _ = Promise::new(claim.depositor_id).transfer(refund);
+        }
+
+        token_id
+    }
+
+    // Signed by a claim key, creates the receiver's account through the network's account
+    // creator, funds it with the rest of the key's deposit and mints into it once it exists.
+    // The key is deleted up front so a failed mint in the callback cannot leave it behind.
+    pub fn create_account_and_claim(&mut self, new_account_id: AccountId, new_public_key: PublicKey) -> Promise {
+        let (public_key, claim) = self.take_claim_key();
+        let _ = Promise::new(env::current_account_id()).delete_key(public_key);
+        let mint_storage = env::storage_byte_cost().saturating_mul(ESTIMATED_MINT_STORAGE_BYTES as u128);
+        let balance = claim.deposit.saturating_sub(CLAIM_KEY_ALLOWANCE).saturating_sub(mint_storage);
+
+        let account_creator_id: AccountId = new_account_id
+            .as_str()
+            .rsplit('.')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        ext_account_creator::ext(account_creator_id)
+            .with_attached_deposit(balance)
+            .with_static_gas(GAS_FOR_CREATE_ACCOUNT)
+            .create_account(new_account_id.clone(), new_public_key)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_CLAIM)
+                    .resolve_create_account_and_claim(new_account_id, claim)
+            )
+    }
+
+    #[private]
+    pub fn resolve_create_account_and_claim(
+        &mut self,
+        new_account_id: AccountId,
+        claim: ClaimKey,
+        #[callback_result] result: Result<bool, PromiseError>,
+    ) -> Option<TokenId> {
+        // The key is already gone, refund its deposit if the account could not be created
+        if !matches!(result, Ok(true)) {
+            let refund = claim.deposit.saturating_sub(CLAIM_KEY_ALLOWANCE);
+            let _ = Promise::new(claim.depositor_id).transfer(refund);
+            return None;
+        }
+
+        let (token_id, _) = self.internal_mint(&claim.channel_id, None, &env::current_account_id(), &new_account_id, &MintOptions::default(), MintAuthorization::Trusted);
+        self.log_mint_event(&new_account_id, std::slice::from_ref(&token_id), None);
+
+        Some(token_id)
+    }
+
+    pub fn get_claim_key(&self, public_key: PublicKey) -> Option<ClaimKey> {
+        self.claim_keys.get(&public_key)
+    }
+
+    fn take_claim_key(&mut self) -> (PublicKey, ClaimKey) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Claims must be signed by a claim key"
+        );
+        let public_key = env::signer_account_pk();
+        let claim = self.claim_keys.remove(&public_key).expect("No claim for this key");
+        assert_eq!(
+            claim.generation,
+            self.channel_generations.get(&claim.channel_id).unwrap_or(0),
+            "Claim key belongs to a deleted channel"
+        );
+        (public_key, claim)
+    }
+
+    // Mints into channels priced in a fungible token, `msg` is a JSON `FtMintMessage`.
+    // Storage for these mints is drawn from the channel's storage pool.
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let ft_contract_id = env::predecessor_account_id();
+        let message: FtMintMessage = serde_json::from_str(&msg).expect("Invalid mint message");
+
+        let channel = self.channels.get(&message.channel_id).expect("Channel not found");
+        let ft_price = channel.ft_price.expect("Channel is not priced in a fungible token");
+        assert_eq!(ft_price.token_id, ft_contract_id, "Channel is not priced in this token");
+        assert!(amount.0 >= ft_price.amount.0, "Must transfer {} tokens to mint", ft_price.amount.0);
+
+        let receiver_id = message.receiver_id.unwrap_or_else(|| sender_id.clone());
+        let initial_storage = env::storage_usage();
+        let (token_id, _) = self.internal_mint(&message.channel_id, message.proof, &sender_id, &receiver_id, &MintOptions::default(), MintAuthorization::Proof);
+        self.credit_channel_ft_proceeds(&message.channel_id, &ft_contract_id, ft_price.amount.0);
+        let storage_cost = env::storage_byte_cost()
+            .saturating_mul(env::storage_usage().saturating_sub(initial_storage) as u128);
+        assert!(
+            self.draw_storage_pool(&message.channel_id, &sender_id, storage_cost),
+            "Channel storage pool does not cover the mint storage"
+        );
+
+        self.log_mint_event(&receiver_id, &[token_id], None);
+
+        // Unused tokens are returned to the sender by the token contract
+        PromiseOrValue::Value(U128(amount.0 - ft_price.amount.0))
+    }
+
+    pub fn is_minted(&self, token_id: TokenId) -> bool {
+        self.minted_tokens.contains(&token_id)
+    }
+
+    pub fn get_next_token_number(&self, channel_id: String) -> Option<u64> {
+        self.channels.get(&channel_id).map(|channel| channel.next_token_number)
     }
 
     pub fn get_channel_info(&self, channel_id: String) -> Option<ChannelView> {
@@ -300,319 +1765,4717 @@ impl Contract {
     
 
     pub fn update_merkle_root(&mut self, channel_id: String, new_merkle_root: Vec<u8>) {
-        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only the owner can update the Merkle root");
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can update the Merkle root"
+        );
         let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.root_frozen, "Channel Merkle root is frozen");
         channel.merkle_root = new_merkle_root;
         self.channels.insert(&channel_id, &channel);
     }
 
-    fn verify_merkle_proof(
-        &self,
-        root: &[u8],
-        token_id: &str,
-        proof: &Vec<Vec<u8>>,
-    ) -> bool {
-        let mut hash = env::sha256(token_id.as_bytes());
-        for proof_element in proof {
-            let combined = if hash <= *proof_element {
-                [hash.as_slice(), proof_element].concat()
+    pub fn set_mint_price(&mut self, channel_id: String, mint_price: NearToken) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can set the mint price"
+        );
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.frozen, "Channel is frozen");
+        channel.mint_price = mint_price;
+        self.channels.insert(&channel_id, &channel);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"update_series_price\",\"data\":{{\"series_id\":\"{}\",\"price\":\"{}\"}}}}",
+            channel_id,
+            mint_price.as_yoctonear()
+        ));
+    }
+
+    pub fn withdraw_channel_proceeds(&mut self, channel_id: String, amount: Option<NearToken>) -> Promise {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only the owner can withdraw proceeds");
+        let available = self.channel_proceeds.get(&channel_id).unwrap_or(NearToken::from_yoctonear(0));
+        let amount = amount.unwrap_or(available);
+        assert!(!amount.is_zero(), "Nothing to withdraw");
+        assert!(amount <= available, "Amount exceeds channel proceeds");
+
+        let remaining = available.saturating_sub(amount);
+        if remaining.is_zero() {
+            self.channel_proceeds.remove(&channel_id);
+        } else {
+            self.channel_proceeds.insert(&channel_id, &remaining);
+        }
+        self.total_proceeds = self.total_proceeds.saturating_sub(amount);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"withdraw_series_proceeds\",\"data\":{{\"series_id\":\"{}\",\"amount\":\"{}\"}}}}",
+            channel_id,
+            amount.as_yoctonear()
+        ));
+
+        Promise::new(self.owner_id.clone()).transfer(amount)
+    }
+
+    pub fn get_channel_proceeds(&self, channel_id: String) -> NearToken {
+        self.channel_proceeds.get(&channel_id).unwrap_or(NearToken::from_yoctonear(0))
+    }
+
+    // Splits the account balance into withdrawable proceeds and NEAR locked for storage
+    pub fn get_treasury(&self) -> TreasuryView {
+        TreasuryView {
+            total_proceeds: self.total_proceeds,
+            payee_balances: self.total_payee_balances,
+            storage_pools: self.total_storage_pools,
+            storage_locked: env::storage_byte_cost().saturating_mul(env::storage_usage() as u128),
+            balance: env::account_balance(),
+        }
+    }
+
+    pub fn set_ft_price(&mut self, channel_id: String, ft_price: Option<FtPrice>) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can set the mint price"
+        );
+        if let Some(ft_price) = &ft_price {
+            assert!(ft_price.amount.0 > 0, "Fungible token price must be positive");
+        }
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.frozen, "Channel is frozen");
+        channel.ft_price = ft_price;
+        self.channels.insert(&channel_id, &channel);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"update_series_ft_price\",\"data\":{{\"series_id\":\"{}\",\"ft_price\":{}}}}}",
+            channel_id,
+            serde_json::to_string(&channel.ft_price).unwrap()
+        ));
+    }
+
+    pub fn withdraw_channel_ft_proceeds(&mut self, channel_id: String, token_id: AccountId, amount: Option<U128>) -> Promise {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only the owner can withdraw proceeds");
+        let key = (channel_id.clone(), token_id.clone());
+        let available = self.channel_ft_proceeds.get(&key).unwrap_or(0);
+        let amount = amount.map(|amount| amount.0).unwrap_or(available);
+        assert!(amount > 0, "Nothing to withdraw");
+        assert!(amount <= available, "Amount exceeds channel proceeds");
+
+        // Debit up front, the callback re-credits if the transfer fails
+        if available == amount {
+            self.channel_ft_proceeds.remove(&key);
+            let mut tokens = self.channel_ft_proceeds_tokens.get(&channel_id).unwrap_or_default();
+            tokens.retain(|token| *token != token_id);
+            if tokens.is_empty() {
+                self.channel_ft_proceeds_tokens.remove(&channel_id);
             } else {
-                [proof_element, hash.as_slice()].concat()
-            };
-            hash = env::sha256(&combined);
+                self.channel_ft_proceeds_tokens.insert(&channel_id, &tokens);
+            }
+        } else {
+            self.channel_ft_proceeds.insert(&key, &(available - amount));
         }
-        hash == root
+
+        ext_ft::ext(token_id.clone())
+            .with_attached_deposit(ONE_YOCTO)
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(self.owner_id.clone(), U128(amount), Some(format!("{} proceeds", channel_id)))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_FT_WITHDRAW)
+                    .resolve_ft_withdraw(channel_id, token_id, U128(amount))
+            )
     }
 
-    #[payable]
-    pub fn nft_transfer(
+    #[private]
+    pub fn resolve_ft_withdraw(
         &mut self,
-        receiver_id: AccountId,
-        token_id: TokenId,
-        approval_id: Option<u64>,  // Added for NEP-171 compatibility
-        memo: Option<String>       // Added for NEP-171 compatibility
-    ) {
-        assert_one_yocto();  // Require attached deposit of exactly 1 yoctoNEAR
-        let sender_id = env::predecessor_account_id();
-        
-        // Get sender's tokens
-        let mut sender_tokens = self.owners
-            .get(&sender_id)
-            .expect("Sender does not own this token");
-        
-        // Check if sender owns the specific token
-        assert!(
-            sender_tokens.contains(&token_id),
-            "Sender does not own this token"
+        channel_id: String,
+        token_id: AccountId,
+        amount: U128,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        if result.is_err() {
+            self.credit_channel_ft_proceeds(&channel_id, &token_id, amount.0);
+            return false;
+        }
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"withdraw_series_proceeds\",\"data\":{{\"series_id\":\"{}\",\"token_id\":\"{}\",\"amount\":\"{}\"}}}}",
+            channel_id,
+            token_id,
+            amount.0
+        ));
+        true
+    }
+
+    pub fn get_channel_ft_proceeds(&self, channel_id: String, token_id: AccountId) -> U128 {
+        U128(self.channel_ft_proceeds.get(&(channel_id, token_id)).unwrap_or(0))
+    }
+
+    fn credit_channel_ft_proceeds(&mut self, channel_id: &str, token_id: &AccountId, amount: u128) {
+        if amount == 0 {
+            return;
+        }
+        let key = (channel_id.to_string(), token_id.clone());
+        let balance = self.channel_ft_proceeds.get(&key).unwrap_or(0);
+        self.channel_ft_proceeds.insert(&key, &(balance + amount));
+
+        // Remembers which tokens hold proceeds so `delete_channel` can check all of them
+        if balance == 0 {
+            let channel_id = channel_id.to_string();
+            let mut tokens = self.channel_ft_proceeds_tokens.get(&channel_id).unwrap_or_default();
+            tokens.push(token_id.clone());
+            self.channel_ft_proceeds_tokens.insert(&channel_id, &tokens);
+        }
+    }
+
+    pub fn set_pricing_curve(&mut self, channel_id: String, pricing_curve: Option<PricingCurve>) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can set the mint price"
+        );
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.frozen, "Channel is frozen");
+        if let Some(auction) = &channel.auction {
+            assert!(
+                auction.quantity == 0 || auction.clearing_price.is_some(),
+                "Dutch auction must be settled first"
+            );
+        }
+
+        if let Some(PricingCurve::Exponential { base_price, growth_bps }) = &pricing_curve {
+            assert!(
+                channel.total_possible == u64::MAX
+                    || Self::checked_exponential_price(*base_price, *growth_bps, channel.total_possible - 1).is_some(),
+                "Pricing curve overflows before the channel's max supply"
+            );
+        }
+
+        // Rebates of a settled round stay claimable from its record while the next round runs
+        if let Some(PricingCurve::DutchAuction { .. }) = pricing_curve {
+            let round = self.next_auction_round;
+            self.next_auction_round += 1;
+            channel.auction = Some(AuctionState {
+                round,
+                escrow: NearToken::from_yoctonear(0),
+                quantity: 0,
+                last_price: None,
+                clearing_price: None,
+            });
+        } else if channel.auction.as_ref().is_some_and(|auction| auction.clearing_price.is_none()) {
+            channel.auction = None;
+        }
+        channel.pricing_curve = pricing_curve;
+        self.channels.insert(&channel_id, &channel);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"update_series_pricing_curve\",\"data\":{{\"series_id\":\"{}\",\"pricing_curve\":{}}}}}",
+            channel_id,
+            serde_json::to_string(&channel.pricing_curve).unwrap()
+        ));
+    }
+
+    // Fixes the clearing price at the last price paid (or the floor without sales), moves the
+    // cleared amount to the channel proceeds and the rebates to the round's `SettledAuction`
+    pub fn settle_dutch_auction(&mut self, channel_id: String) -> NearToken {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can settle auctions"
+        );
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        let floor_price = match &channel.pricing_curve {
+            Some(PricingCurve::DutchAuction { floor_price, .. }) => *floor_price,
+            _ => env::panic_str("Channel is not a Dutch auction"),
+        };
+        let mut auction = channel.auction.take().expect("Channel is not a Dutch auction");
+        assert!(auction.clearing_price.is_none(), "Dutch auction already settled");
+
+        let clearing_price = auction.last_price.unwrap_or(floor_price);
+        let cleared = clearing_price.saturating_mul(auction.quantity as u128);
+        let rebates = auction.escrow.saturating_sub(cleared);
+        if !rebates.is_zero() {
+            self.auction_rounds.insert(&auction.round, &SettledAuction {
+                channel_id: channel_id.clone(),
+                clearing_price,
+                escrow: rebates,
+            });
+        }
+        auction.escrow = NearToken::from_yoctonear(0);
+        auction.clearing_price = Some(clearing_price);
+        channel.auction = Some(auction);
+        self.channels.insert(&channel_id, &channel);
+        self.credit_channel_proceeds(&channel_id, cleared);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"settle_series_auction\",\"data\":{{\"series_id\":\"{}\",\"clearing_price\":\"{}\"}}}}",
+            channel_id,
+            clearing_price.as_yoctonear()
+        ));
+
+        clearing_price
+    }
+
+    // Pays out the caller's rebate of a settled round, the round being `AuctionState::round`
+    pub fn claim_auction_rebate(&mut self, round: u64) -> Promise {
+        assert!(!self.paused, "Contract is paused");
+        let account_id = env::predecessor_account_id();
+        let mut settled = self.auction_rounds.get(&round).expect("No auction payments to rebate");
+        if let Some(channel) = self.channels.get(&settled.channel_id) {
+            self.assert_not_paused(&channel);
+        }
+        let payment = self.auction_payments
+            .remove(&(round, account_id.clone()))
+            .expect("No auction payments to rebate");
+        let rebate = payment.total_paid.saturating_sub(settled.clearing_price.saturating_mul(payment.quantity as u128));
+        assert!(!rebate.is_zero(), "No auction payments to rebate");
+
+        settled.escrow = settled.escrow.saturating_sub(rebate);
+        if settled.escrow.is_zero() {
+            self.auction_rounds.remove(&round);
+        } else {
+            self.auction_rounds.insert(&round, &settled);
+        }
+
+        Promise::new(account_id).transfer(rebate)
+    }
+
+    pub fn get_auction_payment(&self, round: u64, account_id: AccountId) -> Option<AuctionPayment> {
+        self.auction_payments.get(&(round, account_id))
+    }
+
+    pub fn get_settled_auction(&self, round: u64) -> Option<SettledAuction> {
+        self.auction_rounds.get(&round)
+    }
+
+    // Total NEAR price of the next `quantity` mints, excluding storage
+    pub fn get_mint_price(&self, channel_id: String, quantity: u64) -> NearToken {
+        let channel = self.channels.get(&channel_id).expect("Channel not found");
+        self.quote_mint_price(&channel, quantity)
+    }
+
+    fn quote_mint_price(&self, channel: &Channel, quantity: u64) -> NearToken {
+        let curve = match &channel.pricing_curve {
+            Some(curve) => curve,
+            None => return channel.mint_price.saturating_mul(quantity as u128),
+        };
+
+        match curve {
+            PricingCurve::Linear { base_price, increment } => (0..quantity).fold(NearToken::from_yoctonear(0), |total, offset| {
+                let price = increment.saturating_mul((channel.total_supply + offset) as u128);
+                total.saturating_add(base_price.saturating_add(price))
+            }),
+            PricingCurve::Exponential { base_price, growth_bps } => (0..quantity).fold(NearToken::from_yoctonear(0), |total, offset| {
+                let price = Self::exponential_price(*base_price, *growth_bps, channel.total_supply + offset);
+                total.saturating_add(price)
+            }),
+            PricingCurve::DutchAuction { start_price, floor_price, start_time, duration } => {
+                let price = match channel.auction.as_ref().and_then(|auction| auction.clearing_price) {
+                    Some(clearing_price) => clearing_price,
+                    None => {
+                        let elapsed = env::block_timestamp().saturating_sub(*start_time);
+                        if elapsed >= *duration {
+                            *floor_price
+                        } else {
+                            let decay = start_price.saturating_sub(*floor_price).as_yoctonear()
+                                * elapsed as u128 / *duration as u128;
+                            start_price.saturating_sub(NearToken::from_yoctonear(decay))
+                        }
+                    }
+                };
+                price.saturating_mul(quantity as u128)
+            }
+        }
+    }
+
+    // Prices past what fits in a u128 are capped, so a runaway curve stops minting instead of panicking
+    fn exponential_price(base_price: NearToken, growth_bps: u32, supply: u64) -> NearToken {
+        Self::checked_exponential_price(base_price, growth_bps, supply)
+            .unwrap_or(NearToken::from_yoctonear(u128::MAX))
+    }
+
+    // base_price * (1 + growth_bps / 10000) ^ supply in 9-decimal fixed point, the small scale
+    // leaves room for the product of two factors before dividing it back down
+    fn checked_exponential_price(base_price: NearToken, growth_bps: u32, supply: u64) -> Option<NearToken> {
+        const SCALE: u128 = 1_000_000_000;
+        let mut factor = SCALE;
+        let mut step = SCALE + SCALE / 10_000 * growth_bps as u128;
+        let mut exponent = supply;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                factor = factor.checked_mul(step)? / SCALE;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                step = step.checked_mul(step)? / SCALE;
+            }
+        }
+        let base = base_price.as_yoctonear();
+        let price = (base / SCALE).checked_mul(factor)?
+            .checked_add((base % SCALE).checked_mul(factor)? / SCALE)?;
+        Some(NearToken::from_yoctonear(price))
+    }
+
+    // Unsettled Dutch auction payments go to escrow, everything else to the channel proceeds
+    // Referrers are only rewarded outside of an unsettled Dutch auction
+    fn record_mint_payment(
+        &mut self,
+        channel_id: &String,
+        mut channel: Channel,
+        price: NearToken,
+        payer_id: &AccountId,
+        referrer_id: Option<&AccountId>,
+    ) {
+        let auction = match channel.auction.as_mut() {
+            Some(auction) if auction.clearing_price.is_none() => auction,
+            _ => {
+                let mut price = price;
+                if let Some(referrer_id) = referrer_id {
+                    let reward = NearToken::from_yoctonear(price.as_yoctonear() * channel.referral_bps as u128 / 10_000);
+                    self.credit_payee(referrer_id, reward);
+                    price = price.saturating_sub(reward);
+                }
+                return self.credit_channel_proceeds(channel_id, price);
+            }
+        };
+
+        auction.escrow = auction.escrow.saturating_add(price);
+        auction.quantity += 1;
+        auction.last_price = Some(price);
+        let round = auction.round;
+        self.channels.insert(channel_id, &channel);
+
+        let key = (round, payer_id.clone());
+        let mut payment = self.auction_payments
+            .get(&key)
+            .unwrap_or(AuctionPayment {
+                round,
+                total_paid: NearToken::from_yoctonear(0),
+                quantity: 0,
+            });
+        payment.total_paid = payment.total_paid.saturating_add(price);
+        payment.quantity += 1;
+        self.auction_payments.insert(&key, &payment);
+    }
+
+    pub fn set_revenue_splits(&mut self, channel_id: String, revenue_splits: Vec<RevenueSplit>) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can set revenue splits"
+        );
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.frozen && !channel.splits_frozen, "Revenue splits are frozen");
+        assert!(revenue_splits.len() <= MAX_REVENUE_SPLITS, "Too many revenue splits");
+        let total_bps: u32 = revenue_splits.iter().map(|split| split.bps as u32).sum();
+        assert!(total_bps <= 10_000, "Revenue splits exceed 10000 basis points");
+        for (index, split) in revenue_splits.iter().enumerate() {
+            assert!(
+                revenue_splits[..index].iter().all(|other| other.account_id != split.account_id),
+                "Duplicate revenue split payee"
+            );
+        }
+
+        channel.revenue_splits = revenue_splits;
+        self.channels.insert(&channel_id, &channel);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"update_series_splits\",\"data\":{{\"series_id\":\"{}\",\"revenue_splits\":{}}}}}",
+            channel_id,
+            serde_json::to_string(&channel.revenue_splits).unwrap()
+        ));
+    }
+
+    pub fn freeze_revenue_splits(&mut self, channel_id: String) {
+        let mut channel = self.get_channel_to_freeze(&channel_id);
+        channel.splits_frozen = true;
+        self.channels.insert(&channel_id, &channel);
+        self.log_freeze_event(&channel_id, "revenue_splits");
+    }
+
+    pub fn withdraw_payee_balance(&mut self) -> Promise {
+        assert!(!self.paused, "Contract is paused");
+        let account_id = env::predecessor_account_id();
+        let balance = self.payee_balances.remove(&account_id).expect("Nothing to withdraw");
+        self.total_payee_balances = self.total_payee_balances.saturating_sub(balance);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"withdraw_payee_balance\",\"data\":{{\"account_id\":\"{}\",\"amount\":\"{}\"}}}}",
+            account_id,
+            balance.as_yoctonear()
+        ));
+
+        Promise::new(account_id).transfer(balance)
+    }
+
+    pub fn get_payee_balance(&self, account_id: AccountId) -> NearToken {
+        self.payee_balances.get(&account_id).unwrap_or(NearToken::from_yoctonear(0))
+    }
+
+    pub fn set_referral_rate(&mut self, channel_id: String, referral_bps: u16) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can set the referral rate"
+        );
+        assert!(referral_bps <= 10_000, "Referral rate exceeds 10000 basis points");
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.frozen, "Channel is frozen");
+        channel.referral_bps = referral_bps;
+        self.channels.insert(&channel_id, &channel);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"update_series_referral_rate\",\"data\":{{\"series_id\":\"{}\",\"referral_bps\":{}}}}}",
+            channel_id,
+            referral_bps
+        ));
+    }
+
+    pub fn get_referral_count(&self, channel_id: String, account_id: AccountId) -> u64 {
+        self.referral_counts.get(&(self.channel_scope(&channel_id), account_id)).unwrap_or(0)
+    }
+
+    #[payable]
+    pub fn fund_storage_pool(&mut self, channel_id: String) {
+        assert!(self.channels.get(&channel_id).is_some(), "Channel not found");
+        let amount = env::attached_deposit();
+        assert!(!amount.is_zero(), "Must attach NEAR to fund the storage pool");
+
+        let mut pool = self.storage_pools.get(&channel_id).unwrap_or(StoragePool {
+            balance: NearToken::from_yoctonear(0),
+            per_account_limit: None,
+            last_mint_cost: None,
+        });
+        pool.balance = pool.balance.saturating_add(amount);
+        self.storage_pools.insert(&channel_id, &pool);
+        self.total_storage_pools = self.total_storage_pools.saturating_add(amount);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"fund_series_storage_pool\",\"data\":{{\"series_id\":\"{}\",\"sponsor_id\":\"{}\",\"amount\":\"{}\"}}}}",
+            channel_id,
+            env::predecessor_account_id(),
+            amount.as_yoctonear()
+        ));
+    }
+
+    pub fn set_sponsorship_limit(&mut self, channel_id: String, per_account_limit: Option<u64>) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can set sponsorship limits"
+        );
+        assert!(self.channels.get(&channel_id).is_some(), "Channel not found");
+        let mut pool = self.storage_pools.get(&channel_id).unwrap_or(StoragePool {
+            balance: NearToken::from_yoctonear(0),
+            per_account_limit: None,
+            last_mint_cost: None,
+        });
+        pool.per_account_limit = per_account_limit;
+        self.storage_pools.insert(&channel_id, &pool);
+    }
+
+    pub fn withdraw_storage_pool(&mut self, channel_id: String, amount: Option<NearToken>) -> Promise {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only the owner can withdraw storage pools");
+        let mut pool = self.storage_pools.get(&channel_id).expect("Channel has no storage pool");
+        let amount = amount.unwrap_or(pool.balance);
+        assert!(!amount.is_zero(), "Nothing to withdraw");
+        assert!(amount <= pool.balance, "Amount exceeds storage pool balance");
+
+        pool.balance = pool.balance.saturating_sub(amount);
+        self.storage_pools.insert(&channel_id, &pool);
+        self.total_storage_pools = self.total_storage_pools.saturating_sub(amount);
+
+        Promise::new(self.owner_id.clone()).transfer(amount)
+    }
+
+    pub fn get_storage_pool(&self, channel_id: String) -> Option<StoragePoolView> {
+        self.storage_pools.get(&channel_id).map(|pool| {
+            let mint_cost = pool.last_mint_cost.unwrap_or_else(|| {
+                env::storage_byte_cost().saturating_mul(ESTIMATED_MINT_STORAGE_BYTES as u128)
+            });
+            StoragePoolView {
+                balance: pool.balance,
+                per_account_limit: pool.per_account_limit,
+                estimated_remaining_mints: (pool.balance.as_yoctonear() / mint_cost.as_yoctonear().max(1)) as u64,
+            }
+        })
+    }
+
+    pub fn get_sponsored_mints(&self, channel_id: String, account_id: AccountId) -> u64 {
+        self.sponsored_mints.get(&(self.channel_scope(&channel_id), account_id)).unwrap_or(0)
+    }
+
+    // Returns false when the pool can't sponsor this mint, the caller then has to pay
+    fn draw_storage_pool(&mut self, channel_id: &String, account_id: &AccountId, storage_cost: NearToken) -> bool {
+        let mut pool = match self.storage_pools.get(channel_id) {
+            Some(pool) if pool.balance >= storage_cost => pool,
+            _ => return false,
+        };
+        let key = (self.channel_scope(channel_id), account_id.clone());
+        let sponsored_mints = self.sponsored_mints.get(&key).unwrap_or(0);
+        if pool.per_account_limit.is_some_and(|limit| sponsored_mints >= limit) {
+            return false;
+        }
+
+        pool.balance = pool.balance.saturating_sub(storage_cost);
+        pool.last_mint_cost = Some(storage_cost);
+        self.storage_pools.insert(channel_id, &pool);
+        self.total_storage_pools = self.total_storage_pools.saturating_sub(storage_cost);
+        self.sponsored_mints.insert(&key, &(sponsored_mints + 1));
+        true
+    }
+
+    fn credit_payee(&mut self, account_id: &AccountId, amount: NearToken) {
+        if amount.is_zero() {
+            return;
+        }
+        let balance = self.payee_balances.get(account_id).unwrap_or(NearToken::from_yoctonear(0));
+        self.payee_balances.insert(account_id, &balance.saturating_add(amount));
+        self.total_payee_balances = self.total_payee_balances.saturating_add(amount);
+    }
+
+    // Pays the channel's collaborators their share and keeps the remainder as channel proceeds
+    fn credit_channel_proceeds(&mut self, channel_id: &String, amount: NearToken) {
+        if amount.is_zero() {
+            return;
+        }
+        let revenue_splits = self.channels
+            .get(channel_id)
+            .map(|channel| channel.revenue_splits)
+            .unwrap_or_default();
+        let mut amount = amount;
+        let total = amount.as_yoctonear();
+        for split in revenue_splits {
+            let share = NearToken::from_yoctonear(total * split.bps as u128 / 10_000);
+            self.credit_payee(&split.account_id, share);
+            amount = amount.saturating_sub(share);
+        }
+        if amount.is_zero() {
+            return;
+        }
+
+        let balance = self.channel_proceeds.get(channel_id).unwrap_or(NearToken::from_yoctonear(0));
+        self.channel_proceeds.insert(channel_id, &balance.saturating_add(amount));
+        self.total_proceeds = self.total_proceeds.saturating_add(amount);
+    }
+
+    pub fn freeze_channel_metadata(&mut self, channel_id: String) {
+        let mut channel = self.get_channel_to_freeze(&channel_id);
+        channel.metadata_frozen = true;
+        self.channels.insert(&channel_id, &channel);
+        self.log_freeze_event(&channel_id, "metadata");
+    }
+
+    pub fn freeze_merkle_root(&mut self, channel_id: String) {
+        let mut channel = self.get_channel_to_freeze(&channel_id);
+        channel.root_frozen = true;
+        self.channels.insert(&channel_id, &channel);
+        self.log_freeze_event(&channel_id, "merkle_root");
+    }
+
+    // Freezes metadata and root together and locks the channel's remaining settings
+    pub fn freeze_channel(&mut self, channel_id: String) {
+        let mut channel = self.get_channel_to_freeze(&channel_id);
+        channel.metadata_frozen = true;
+        channel.root_frozen = true;
+        channel.frozen = true;
+        self.channels.insert(&channel_id, &channel);
+        self.log_freeze_event(&channel_id, "channel");
+    }
+
+    fn get_channel_to_freeze(&self, channel_id: &String) -> Channel {
+        assert!(
+            self.can_manage_channel(channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can freeze channels"
+        );
+        self.channels.get(channel_id).expect("Channel not found")
+    }
+
+    fn log_freeze_event(&self, channel_id: &str, scope: &str) {
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"freeze_series\",\"data\":{{\"series_id\":\"{}\",\"scope\":\"{}\"}}}}",
+            channel_id,
+            scope
+        ));
+    }
+
+    // Checks mint permissions and records the next token of the channel for the receiver
+    fn internal_mint(
+        &mut self,
+        channel_id: &String,
+        proof: Option<Vec<Vec<u8>>>,
+        minter_id: &AccountId,
+        receiver_id: &AccountId,
+        options: &MintOptions,
+        authorization: MintAuthorization,
+    ) -> (TokenId, Channel) {
+        let requested_number = options.token_number;
+        let mut channel = self.channels.get(channel_id).expect("Channel not found");
+        self.assert_not_paused(&channel);
+        assert!(!channel.archived, "Channel is archived");
+        let tier_index = options.tier.as_ref().map(|tier| {
+            assert!(channel.rarity_tiers.is_empty(), "Randomized channels assign tiers");
+            let index = channel.tiers
+                .iter()
+                .position(|channel_tier| channel_tier.name == *tier)
+                .expect("Tier not found");
+            assert!(channel.tiers[index].minted < channel.tiers[index].supply, "Tier is sold out");
+            index
+        });
+
+        // Sequential mints take the next number that is neither reserved nor taken
+        let token_number = match requested_number {
+            Some(token_number) => {
+                assert!(channel.rarity_tiers.is_empty(), "Randomized channels assign token numbers");
+                assert!(token_number > 0, "Token numbers start at 1");
+                assert!(!channel.minted_tokens.contains(&token_number), "Token number already minted");
+                if Self::is_reserved(&channel, token_number) {
+                    assert!(
+                        self.can_manage_channel(channel_id, minter_id),
+                        "Token number is reserved for the channel owner"
+                    );
+                }
+                token_number
+            }
+            None if channel.rarity_tiers.is_empty() => {
+                let mut token_number = channel.next_token_number;
+                loop {
+                    if let Some(range) = channel.reserved_ranges
+                        .iter()
+                        .find(|range| range.start <= token_number && token_number <= range.end)
+                    {
+                        token_number = range.end + 1;
+                    } else if channel.minted_tokens.contains(&token_number) {
+                        token_number += 1;
+                    } else {
+                        break token_number;
+                    }
+                }
+            }
+            None => channel.next_token_number,
+        };
+        
+        // Remove total_possible check since we want unlimited minting
+        
+        // Verify proof for requested numbers, tiers and every sequential mint but token 1, minters, allowance
+        // holders and channel managers minting reserved numbers are trusted without one.
+        // Random token numbers are unknown up front, so randomized channels prove the receiver instead.
+        let is_trusted_minter = self.is_trusted_minter(
+            channel_id,
+            &channel,
+            minter_id,
+            receiver_id,
+            requested_number.is_some() && Self::is_reserved(&channel, token_number),
+            authorization
+        );
+        let is_first_token = requested_number.is_none() && channel.rarity_tiers.is_empty() && token_number == 1;
+        if (requested_number.is_some() || tier_index.is_some() || !is_first_token) && !is_trusted_minter {
+            let leaf = if channel.rarity_tiers.is_empty() {
+                format!("{}:{}", channel_id, token_number)
+            } else {
+                // Each proven receiver mints once, like each proven number in sequential channels
+                assert!(
+                    self.random_mint_receivers.insert(&(self.channel_scope(channel_id), receiver_id.clone())),
+                    "Receiver already minted from this channel"
+                );
+                format!("{}:{}", channel_id, receiver_id)
+            };
+            let merkle_root = match tier_index {
+                Some(index) => &channel.tiers[index].merkle_root,
+                None => &channel.merkle_root,
+            };
+            self.assert_valid_proof(merkle_root, &leaf, proof);
+        }
+
+        let (rarity_tier, token_number) = if channel.rarity_tiers.is_empty() {
+            (None, token_number)
+        } else {
+            let (tier, token_number) = Self::draw_random_token(&mut channel);
+            (Some(tier), token_number)
+        };
+        let token_id = format!("{}:{}", channel_id, token_number);
+        if let Some(tier) = rarity_tier {
+            self.token_tiers.insert(&token_id, &tier);
+        }
+        
+        // Update states
+        channel.minted_tokens.insert(&token_number);
+        if !channel.rarity_tiers.is_empty() {
+            channel.next_token_number += 1;
+        } else if requested_number.is_none() {
+            channel.next_token_number = token_number + 1;
+        }
+        channel.total_supply += 1;
+        if let Some(index) = tier_index {
+            channel.tiers[index].minted += 1;
+            self.token_tiers.insert(&token_id, &channel.tiers[index].name);
+        }
+        self.channels.insert(channel_id, &channel);
+        
+        self.minted_tokens.insert(&token_id);
+        let mut owner_tokens = self.owners
+            .get(receiver_id)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKey::OwnerTokens {
+                account_id: receiver_id.clone(),
+            }));
+        owner_tokens.insert(&token_id);
+        self.owners.insert(receiver_id, &owner_tokens);
+        self.token_owners.insert(&token_id, receiver_id);
+        self.token_minted_at.insert(&token_id, &env::block_timestamp());
+
+        (token_id, channel)
+    }
+
+    // Minters, allowance holders and trusted callers mint without a proof, channel managers too
+    // for reserved numbers. Proofs on commit-reveal channels only count once revealed, so they
+    // cannot be front-run from the mempool.
+    fn is_trusted_minter(
+        &mut self,
+        channel_id: &str,
+        channel: &Channel,
+        minter_id: &AccountId,
+        receiver_id: &AccountId,
+        reserved: bool,
+        authorization: MintAuthorization,
+    ) -> bool {
+        let is_trusted = authorization == MintAuthorization::Trusted
+            || self.account_has_channel_role(channel_id, ChannelRole::Minter, minter_id)
+            || self.use_minter_allowance(channel_id, minter_id, receiver_id)
+            || (reserved && self.can_manage_channel(channel_id, minter_id));
+        assert!(
+            is_trusted
+                || authorization == MintAuthorization::Revealed
+                || channel.commit_reveal_blocks.is_none(),
+            "Channel requires commit-reveal minting"
+        );
+        is_trusted
+    }
+
+    fn assert_valid_proof(&self, merkle_root: &[u8], leaf: &str, proof: Option<Vec<Vec<u8>>>) {
+        let proof = proof.expect("Proof required for minting");
+        assert!(
+            self.verify_merkle_proof(merkle_root, leaf, &proof),
+            "Invalid proof"
+        );
+    }
+
+    fn get_tier<'a>(channel: &'a Channel, name: &str) -> &'a ChannelTier {
+        channel.tiers
+            .iter()
+            .find(|tier| tier.name == *name)
+            .expect("Tier not found")
+    }
+
+    // Adds a tier or updates the tier of the same name, whose supply cannot drop below what it minted
+    pub fn set_channel_tier(&mut self, channel_id: String, tier: ChannelTierConfig) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can set channel tiers"
+        );
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.frozen, "Channel is frozen");
+        assert!(channel.rarity_tiers.is_empty(), "Randomized channels assign tiers");
+        validate_template(&tier.media);
+
+        match channel.tiers.iter_mut().find(|existing| existing.name == tier.name) {
+            Some(existing) => {
+                assert!(tier.supply >= existing.minted, "Tier supply is below its minted tokens");
+                assert!(
+                    !channel.root_frozen || existing.merkle_root == tier.merkle_root,
+                    "Channel Merkle root is frozen"
+                );
+                assert!(
+                    !channel.metadata_frozen
+                        || (existing.media == tier.media && existing.animation_url == tier.animation_url),
+                    "Channel metadata is frozen"
+                );
+                existing.merkle_root = tier.merkle_root;
+                existing.media = tier.media;
+                existing.animation_url = tier.animation_url;
+                existing.supply = tier.supply;
+                existing.price = tier.price;
+            }
+            None => {
+                // A new tier brings its own root and media, so either freeze rules it out
+                assert!(!channel.root_frozen, "Channel Merkle root is frozen");
+                assert!(!channel.metadata_frozen, "Channel metadata is frozen");
+                assert!(channel.tiers.len() < MAX_CHANNEL_TIERS, "Too many channel tiers");
+                channel.tiers.push(ChannelTier {
+                    name: tier.name,
+                    merkle_root: tier.merkle_root,
+                    media: tier.media,
+                    animation_url: tier.animation_url,
+                    supply: tier.supply,
+                    price: tier.price,
+                    minted: 0,
+                });
+            }
+        }
+        self.channels.insert(&channel_id, &channel);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"update_series_tiers\",\"data\":{{\"series_id\":\"{}\",\"tiers\":{}}}}}",
+            channel_id,
+            serde_json::to_string(&channel.tiers).unwrap()
+        ));
+    }
+
+    pub fn get_channel_tiers(&self, channel_id: String) -> Vec<ChannelTier> {
+        self.channels
+            .get(&channel_id)
+            .map(|channel| channel.tiers)
+            .unwrap_or_default()
+    }
+
+    pub fn get_token_tier(&self, token_id: TokenId) -> Option<String> {
+        self.token_tiers.get(&token_id)
+    }
+
+    fn is_reserved(channel: &Channel, token_number: u64) -> bool {
+        channel.reserved_ranges
+            .iter()
+            .any(|range| range.start <= token_number && token_number <= range.end)
+    }
+
+    // Reserves token numbers `start..=end` for the channel's managers, sequential mints skip them
+    pub fn reserve_token_range(&mut self, channel_id: String, start: u64, end: u64) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can reserve token numbers"
+        );
+        assert!(start > 0 && start <= end, "Invalid token number range");
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.frozen, "Channel is frozen");
+        assert!(channel.rarity_tiers.is_empty(), "Randomized channels assign token numbers");
+        assert!(channel.reserved_ranges.len() < MAX_RESERVED_RANGES, "Too many reserved ranges");
+        channel.reserved_ranges.push(ReservedRange { start, end });
+        self.channels.insert(&channel_id, &channel);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"reserve_token_range\",\"data\":{{\"series_id\":\"{}\",\"start\":{},\"end\":{}}}}}",
+            channel_id,
+            start,
+            end
+        ));
+    }
+
+    // Releases a reserved range, unminted numbers in it go back to sequential minting
+    pub fn release_token_range(&mut self, channel_id: String, start: u64, end: u64) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can reserve token numbers"
+        );
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(!channel.frozen, "Channel is frozen");
+        let index = channel.reserved_ranges
+            .iter()
+            .position(|range| range.start == start && range.end == end)
+            .expect("Reserved range not found");
+        channel.reserved_ranges.remove(index);
+        channel.next_token_number = channel.next_token_number.min(start);
+        self.channels.insert(&channel_id, &channel);
+    }
+
+    // Picks a tier weighted by `weight` among tiers with supply left, then an unminted number of
+    // that tier with a sparse Fisher-Yates shuffle, returning the tier name and token number
+    fn draw_random_token(channel: &mut Channel) -> (String, u64) {
+        let seed = env::sha256([env::random_seed(), channel.next_token_number.to_le_bytes().to_vec()].concat());
+        let tier_roll = u64::from_le_bytes(seed[0..8].try_into().unwrap());
+        let number_roll = u64::from_le_bytes(seed[8..16].try_into().unwrap());
+
+        let total_weight: u64 = channel.rarity_tiers
+            .iter()
+            .filter(|tier| tier.remaining > 0)
+            .map(|tier| tier.weight as u64)
+            .sum();
+        assert!(total_weight > 0, "Channel is sold out");
+        let mut roll = tier_roll % total_weight;
+        let index = channel.rarity_tiers
+            .iter()
+            .position(|tier| {
+                if tier.remaining == 0 {
+                    return false;
+                }
+                if roll < tier.weight as u64 {
+                    return true;
+                }
+                roll -= tier.weight as u64;
+                false
+            })
+            .unwrap();
+
+        let tier = &mut channel.rarity_tiers[index];
+        let last = tier.remaining - 1;
+        let position = number_roll % tier.remaining;
+        let key = (index as u32, position);
+        let offset = channel.random_swaps.get(&key).unwrap_or(position);
+        // Move the last undrawn offset into the drawn slot
+        let last_offset = channel.random_swaps.remove(&(index as u32, last)).unwrap_or(last);
+        if position != last {
+            channel.random_swaps.insert(&key, &last_offset);
+        }
+        tier.remaining = last;
+
+        (tier.name.clone(), tier.first_number + offset)
+    }
+
+    pub fn get_rarity_tiers(&self, channel_id: String) -> Vec<RarityTier> {
+        self.channels
+            .get(&channel_id)
+            .map(|channel| channel.rarity_tiers)
+            .unwrap_or_default()
+    }
+
+    // Mints one token to each of `receivers[cursor..]` without proofs or mint price, stopping
+    // before gas runs out. Returns the cursor to resume from, or `None` once every receiver got
+    // a token. The attached deposit covers the storage of the whole batch.
+    #[payable]
+    pub fn airdrop(&mut self, channel_id: String, receivers: Vec<AccountId>, cursor: Option<u64>) -> Option<u64> {
+        let minter_id = env::predecessor_account_id();
+        assert!(self.is_admin(&minter_id), "Only the owner or an admin can airdrop");
+        let initial_storage = env::storage_usage();
+
+        let mut index = cursor.unwrap_or(0) as usize;
+        let mut minted = Vec::new();
+        while index < receivers.len() {
+            if env::prepaid_gas().saturating_sub(env::used_gas()) < PAGED_JOB_GAS_RESERVE {
+                break;
+            }
+            let (token_id, _) = self.internal_mint(&channel_id, None, &minter_id, &receivers[index], &MintOptions::default(), MintAuthorization::Trusted);
+            minted.push((receivers[index].clone(), token_id));
+            index += 1;
+        }
+        for batch in minted.chunks(AIRDROP_EVENT_BATCH) {
+            self.log_mint_batch_event(batch);
+        }
+
+        let storage_cost = env::storage_byte_cost()
+            .saturating_mul(env::storage_usage().saturating_sub(initial_storage) as u128);
+        assert!(
+            env::attached_deposit() >= storage_cost,
+            "Must attach {} yoctoNEAR to cover the airdrop storage",
+            storage_cost.as_yoctonear()
+        );
+        let refund = env::attached_deposit().saturating_sub(storage_cost);
+        if !refund.is_zero() {
+            let _ = Promise::new(minter_id).transfer(refund);
+        }
+
+        if index < receivers.len() {
+            Some(index as u64)
+        } else {
+            None
+        }
+    }
+
+    // Starts a job that `run_snapshot_airdrop` works through over the source's current minted tokens.
+    // Holders are read as the job reaches their token, so the source must stay paused until it is
+    // done, which keeps its tokens from moving to accounts that were already rewarded.
+    pub fn start_snapshot_airdrop(
+        &mut self,
+        source_channel_id: String,
+        target_channel_id: String,
+        reward: SnapshotReward,
+        quantity: u64,
+    ) -> u64 {
+        assert!(self.is_admin(&env::predecessor_account_id()), "Only the owner or an admin can airdrop");
+        assert!(quantity > 0 && quantity <= MAX_SNAPSHOT_QUANTITY, "Invalid airdrop quantity");
+        let source = self.channels.get(&source_channel_id).expect("Source channel not found");
+        assert!(source.paused, "Pause the source channel before snapshotting it");
+        assert_ne!(source_channel_id, target_channel_id, "Source and target channels must differ");
+        assert!(self.channels.get(&target_channel_id).is_some(), "Target channel not found");
+
+        let job_id = self.next_snapshot_airdrop_id;
+        self.next_snapshot_airdrop_id += 1;
+        self.snapshot_airdrops.insert(&job_id, &SnapshotAirdrop {
+            source_channel_id,
+            target_channel_id,
+            reward,
+            quantity,
+            total: source.minted_tokens.len(),
+            cursor: 0,
+            minted_at_cursor: 0,
+            rewarded: 0,
+            minted: 0,
+        });
+
+        job_id
+    }
+
+    // Runs one gas-bounded page of a snapshot airdrop, the attached deposit covers the page's
+    // storage. Holders are looked up when their token is reached, burned tokens are skipped and
+    // per-holder jobs reward each account once. Returns whether the job is done.
+    #[payable]
+    pub fn run_snapshot_airdrop(&mut self, job_id: u64) -> bool {
+        let minter_id = env::predecessor_account_id();
+        assert!(self.is_admin(&minter_id), "Only the owner or an admin can airdrop");
+        let mut job = self.snapshot_airdrops.get(&job_id).expect("Snapshot airdrop not found");
+        let source = self.channels.get(&job.source_channel_id).expect("Source channel not found");
+        assert!(source.paused, "Source channel was unpaused during the snapshot");
+        let initial_storage = env::storage_usage();
+
+        let mut minted = Vec::new();
+        while job.cursor < job.total {
+            if env::prepaid_gas().saturating_sub(env::used_gas()) < PAGED_JOB_GAS_RESERVE {
+                break;
+            }
+            let token_number = source.minted_tokens.as_vector().get(job.cursor).unwrap();
+            let holder_id = self.token_owners
+                .get(&format!("{}:{}", job.source_channel_id, token_number))
+                .filter(|holder_id| {
+                    job.reward == SnapshotReward::PerToken
+                        || !self.snapshot_airdrop_holders.contains(&(job_id, holder_id.clone()))
+                });
+
+            if let Some(holder_id) = holder_id {
+                if job.minted_at_cursor < job.quantity {
+                    let (token_id, _) = self.internal_mint(&job.target_channel_id, None, &minter_id, &holder_id, &MintOptions::default(), MintAuthorization::Trusted);
+                    minted.push((holder_id, token_id));
+                    job.minted_at_cursor += 1;
+                    job.minted += 1;
+                    continue;
+                }
+                if job.reward == SnapshotReward::PerHolder {
+                    self.snapshot_airdrop_holders.insert(&(job_id, holder_id));
+                }
+                job.rewarded += 1;
+            }
+            job.cursor += 1;
+            job.minted_at_cursor = 0;
+        }
+        for batch in minted.chunks(AIRDROP_EVENT_BATCH) {
+            self.log_mint_batch_event(batch);
+        }
+
+        let done = job.cursor >= job.total;
+        self.snapshot_airdrops.insert(&job_id, &job);
+
+        let storage_cost = env::storage_byte_cost()
+            .saturating_mul(env::storage_usage().saturating_sub(initial_storage) as u128);
+        assert!(
+            env::attached_deposit() >= storage_cost,
+            "Must attach {} yoctoNEAR to cover the airdrop storage",
+            storage_cost.as_yoctonear()
+        );
+        let refund = env::attached_deposit().saturating_sub(storage_cost);
+        if !refund.is_zero() {
+            let _ = Promise::new(minter_id).transfer(refund);
+        }
+
+        done
+    }
+
+    pub fn get_snapshot_airdrop(&self, job_id: u64) -> Option<SnapshotAirdrop> {
+        self.snapshot_airdrops.get(&job_id)
+    }
+
+    fn log_mint_batch_event(&self, mints: &[(AccountId, TokenId)]) {
+        let data: Vec<String> = mints
+            .iter()
+            .map(|(owner_id, token_id)| match self.token_tiers.get(token_id) {
+                Some(tier) => format!(
+                    "{{\"owner_id\":\"{}\",\"token_ids\":[\"{}\"],\"memo\":\"tier:{}\"}}",
+                    owner_id, token_id, tier
+                ),
+                None => format!(
+                    "{{\"owner_id\":\"{}\",\"token_ids\":[\"{}\"]}}",
+                    owner_id, token_id
+                ),
+            })
+            .collect();
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"nft_mint\",\"data\":[{}]}}",
+            data.join(",")
+        ));
+    }
+
+    fn log_mint_event(&self, receiver_id: &AccountId, token_ids: &[TokenId], memo: Option<&str>) {
+        // Tokens with a tier get an entry of their own with the tier in the memo
+        let (tiered, untiered): (Vec<_>, Vec<_>) = token_ids
+            .iter()
+            .map(|token_id| (token_id, self.token_tiers.get(token_id)))
+            .partition(|(_, tier)| tier.is_some());
+        let mut data = Vec::new();
+        if !untiered.is_empty() || tiered.is_empty() {
+            let token_ids: Vec<&TokenId> = untiered.into_iter().map(|(token_id, _)| token_id).collect();
+            data.push(format!(
+                "{{\"owner_id\":\"{}\",\"token_ids\":{},\"memo\":{}}}",
+                receiver_id,
+                serde_json::to_string(&token_ids).unwrap(),
+                serde_json::to_string(&memo).unwrap()
+            ));
+        }
+        for (token_id, tier) in tiered {
+            let tier_memo = match memo {
+                Some(memo) => format!("{},tier:{}", memo, tier.unwrap()),
+                None => format!("tier:{}", tier.unwrap()),
+            };
+            data.push(format!(
+                "{{\"owner_id\":\"{}\",\"token_ids\":[\"{}\"],\"memo\":{}}}",
+                receiver_id,
+                token_id,
+                serde_json::to_string(&tier_memo).unwrap()
+            ));
+        }
+
+        // Emit NEP-171 event
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"nft_mint\",\"data\":[{}]}}",
+            data.join(",")
+        ));
+    }
+
+    // Mints `quantity` tokens paid from the attached deposit: the mint price plus storage,
+    // which the channel's storage pool sponsors when nothing is attached
+    fn internal_mint_with_deposit(
+        &mut self,
+        channel_id: &String,
+        proof: Option<Vec<Vec<u8>>>,
+        receiver_id: &AccountId,
+        options: &MintOptions,
+        quantity: u64,
+        authorization: MintAuthorization,
+    ) -> Vec<TokenId> {
+        let initial_storage = env::storage_usage();
+        let (token_ids, total_price) = self.internal_mint_priced(channel_id, proof, receiver_id, options, quantity, authorization);
+        self.charge_mint_deposit(channel_id, initial_storage, total_price);
+        token_ids
+    }
+
+    // Mints `quantity` tokens and records their payment, returning the total price the caller
+    // still has to collect
+    fn internal_mint_priced(
+        &mut self,
+        channel_id: &String,
+        proof: Option<Vec<Vec<u8>>>,
+        receiver_id: &AccountId,
+        options: &MintOptions,
+        quantity: u64,
+        authorization: MintAuthorization,
+    ) -> (Vec<TokenId>, NearToken) {
+        assert!(options.token_number.is_none() || quantity == 1, "A requested token number mints a single token");
+        let minter_id = env::predecessor_account_id();
+        let channel = self.channels.get(channel_id).expect("Channel not found");
+        assert!(channel.ft_price.is_none(), "Channel is priced in a fungible token");
+
+        let mut total_price = NearToken::from_yoctonear(0);
+        let mut token_ids = Vec::new();
+        for _ in 0..quantity {
+            let mint_price = self.channels
+                .get(channel_id)
+                .map(|channel| match &options.tier {
+                    Some(tier) => Self::get_tier(&channel, tier).price,
+                    None => self.quote_mint_price(&channel, 1),
+                })
+                .expect("Channel not found");
+            let (token_id, channel) = self.internal_mint(channel_id, proof.clone(), &minter_id, receiver_id, options, authorization);
+            self.record_mint_payment(channel_id, channel, mint_price, &minter_id, options.referrer_id.as_ref());
+            total_price = total_price.saturating_add(mint_price);
+            token_ids.push(token_id);
+        }
+
+        (token_ids, total_price)
+    }
+
+    // Requires the attached deposit to cover `price` plus the storage used since `initial_storage`
+    // and refunds the rest
+    fn charge_mint_deposit(&mut self, channel_id: &String, initial_storage: u64, price: NearToken) {
+        let minter_id = env::predecessor_account_id();
+        let required_storage = env::storage_usage().saturating_sub(initial_storage);
+        let storage_cost = env::storage_byte_cost().saturating_mul(required_storage as u128);
+        let sponsored = env::attached_deposit().is_zero()
+            && self.draw_storage_pool(channel_id, &minter_id, storage_cost);
+        let required_cost = if sponsored {
+            price
+        } else {
+            price.saturating_add(storage_cost)
+        };
+        
+        assert!(
+            env::attached_deposit() >= required_cost,
+            "Must attach {} yoctoNEAR to cover the mint price and storage",
+            required_cost.as_yoctonear(),
+        );
+
+        // Refund any overpayment
+        let refund = env::attached_deposit().saturating_sub(required_cost);
+        if !refund.is_zero() {
+            let _ = Promise::new(minter_id).transfer(refund);
+        }
+    }
+
+    fn verify_merkle_proof(
+        &self,
+        root: &[u8],
+        token_id: &str,
+        proof: &Vec<Vec<u8>>,
+    ) -> bool {
+        let mut hash = env::sha256(token_id.as_bytes());
+        for proof_element in proof {
+            let combined = if hash <= *proof_element {
+                [hash.as_slice(), proof_element].concat()
+            } else {
+                [proof_element, hash.as_slice()].concat()
+            };
+            hash = env::sha256(&combined);
+        }
+        hash == root
+    }
+
+    #[payable]
+    #[allow(unused_variables)]
+    pub fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,  // Added for NEP-171 compatibility
+        memo: Option<String>       // Added for NEP-171 compatibility
+    ) {
+        assert_one_yocto();  // Require attached deposit of exactly 1 yoctoNEAR
+        self.assert_token_not_paused(&token_id);
+        let sender_id = env::predecessor_account_id();
+        self.internal_transfer(&sender_id, &receiver_id, &token_id, memo);
+    }
+
+    fn internal_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        token_id: &TokenId,
+        memo: Option<String>
+    ) {
+        // Get sender's tokens
+        let mut sender_tokens = self.owners
+            .get(sender_id)
+            .expect("Sender does not own this token");
+        
+        // Check if sender owns the specific token
+        assert!(
+            sender_tokens.contains(token_id),
+            "Sender does not own this token"
+        );
+
+        // Prevent transferring to the same account
+        assert_ne!(
+            sender_id, receiver_id,
+            "The token owner and the receiver should be different"
+        );
+
+        // Remove token from sender
+        sender_tokens.remove(token_id);
+        
+        // Update or remove sender's token set
+        if sender_tokens.is_empty() {
+            self.owners.remove(sender_id);
+        } else {
+            self.owners.insert(sender_id, &sender_tokens);
+        }
+
+        // Add token to receiver
+        let mut receiver_tokens = self.owners
+            .get(receiver_id)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKey::OwnerTokens { 
+                account_id: receiver_id.clone() 
+            }));
+        
+        receiver_tokens.insert(token_id);
+        self.owners.insert(receiver_id, &receiver_tokens);
+        self.token_owners.insert(token_id, receiver_id);
+
+        // Log the transfer with memo if provided
+        let memo_str = memo.unwrap_or_default();
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"nft_transfer\",\"data\":[{{\"authorized_id\":null,\"old_owner_id\":\"{}\",\"new_owner_id\":\"{}\",\"token_ids\":[\"{}\"],\"memo\":\"{}\"}}]}}",
+            sender_id,
+            receiver_id,
+            token_id,
+            memo_str
+        ));
+    }
+
+    #[payable]
+    pub fn nft_burn(&mut self, token_id: TokenId) {
+        self.assert_token_not_paused(&token_id);
+        let owner_id = env::predecessor_account_id();
+        self.internal_burn(&owner_id, &token_id);
+    }
+
+    fn internal_burn(&mut self, owner_id: &AccountId, token_id: &TokenId) {
+        // Verify ownership
+        let mut owner_tokens = self.owners.get(owner_id).expect("Owner has no tokens");
+        assert!(owner_tokens.contains(token_id), "Sender does not own this token");
+
+        // Remove token from owner
+        owner_tokens.remove(token_id);
+        if owner_tokens.is_empty() {
+            self.owners.remove(owner_id);
+        } else {
+            self.owners.insert(owner_id, &owner_tokens);
+        }
+
+        // Remove from minted_tokens
+        self.minted_tokens.remove(token_id);
+        self.token_owners.remove(token_id);
+        self.token_tiers.remove(token_id);
+        self.token_minted_at.remove(token_id);
+        self.token_metadata_overrides.remove(token_id);
+        self.token_states.remove(token_id);
+
+        // Update channel data
+        let (channel_id, _) = token_id.split_once(':').unwrap();
+        let mut channel = self.channels.get(&channel_id.to_string()).expect("Channel not found");
+        channel.total_supply -= 1;
+        self.channels.insert(&channel_id.to_string(), &channel);
+
+        // Emit burn event
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"nft_burn\",\"data\":[{{\"owner_id\":\"{}\",\"token_ids\":[\"{}\"]}}]}}",
+            owner_id, token_id
+        ));
+    }
+
+    pub fn nft_tokens_for_owner(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>
+    ) -> Vec<JsonToken> {
+        // Get the set of tokens for the given account, or create an empty set if none exists
+        let tokens = self.owners.get(&account_id)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKey::OwnerTokens { 
+                account_id: account_id.clone() 
+            }));
+        
+        // Convert from_index from U128 to usize
+        let start = u128::from(from_index.unwrap_or(U128(0))) as usize;
+        let limit = limit.unwrap_or(50) as usize;  // Default limit of 50 tokens
+
+        // Get the tokens with pagination
+        tokens.iter()
+            .skip(start)
+            .take(limit)
+            .filter_map(|token_id| self.nft_token(token_id.clone()))
+            .collect()
+    }
+
+    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> near_sdk::json_types::U128 {
+        near_sdk::json_types::U128(
+            self.owners
+                .get(&account_id)
+                .map(|tokens| tokens.len() as u128)
+                .unwrap_or(0)
+        )
+    }
+
+    pub fn grant_role(&mut self, role: ContractRole, account_id: AccountId) {
+        let caller = env::predecessor_account_id();
+        if role == ContractRole::Admin {
+            assert_eq!(caller, self.owner_id, "Only the owner can grant the Admin role");
+        } else {
+            assert!(self.is_admin(&caller), "Only the owner or an admin can grant roles");
+        }
+
+        let mut members = self.roles
+            .get(&role)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKey::RoleMembers { role }));
+        if members.insert(&account_id) {
+            self.roles.insert(&role, &members);
+            self.log_role_event("role_granted", &role, None, &account_id, &caller);
+        }
+    }
+
+    pub fn revoke_role(&mut self, role: ContractRole, account_id: AccountId) {
+        let caller = env::predecessor_account_id();
+        if role == ContractRole::Admin {
+            assert_eq!(caller, self.owner_id, "Only the owner can revoke the Admin role");
+        } else {
+            assert!(self.is_admin(&caller), "Only the owner or an admin can revoke roles");
+        }
+
+        if let Some(mut members) = self.roles.get(&role) {
+            if members.remove(&account_id) {
+                if members.is_empty() {
+                    self.roles.remove(&role);
+                } else {
+                    self.roles.insert(&role, &members);
+                }
+                self.log_role_event("role_revoked", &role, None, &account_id, &caller);
+            }
+        }
+    }
+
+    pub fn grant_channel_role(&mut self, channel_id: String, role: ChannelRole, account_id: AccountId) {
+        assert!(self.channels.get(&channel_id).is_some(), "Channel not found");
+        let caller = env::predecessor_account_id();
+        self.assert_can_administer_channel_role(&channel_id, role, &caller);
+
+        if self.add_channel_role_member(&channel_id, role, &account_id) {
+            self.log_role_event("role_granted", &role, Some(&channel_id), &account_id, &caller);
+        }
+    }
+
+    pub fn revoke_channel_role(&mut self, channel_id: String, role: ChannelRole, account_id: AccountId) {
+        let caller = env::predecessor_account_id();
+        self.assert_can_administer_channel_role(&channel_id, role, &caller);
+
+        let key = (channel_id.clone(), role);
+        if let Some(mut members) = self.channel_roles.get(&key) {
+            if members.remove(&account_id) {
+                if members.is_empty() {
+                    self.channel_roles.remove(&key);
+                } else {
+                    self.channel_roles.insert(&key, &members);
+                }
+                self.log_role_event("role_revoked", &role, Some(&channel_id), &account_id, &caller);
+            }
+        }
+    }
+
+    pub fn set_minter_allowance(
+        &mut self,
+        channel_id: String,
+        account_id: AccountId,
+        allowance: u64,
+        expires_at: Option<u64>,
+        receivers: Option<Vec<AccountId>>,
+    ) {
+        assert!(self.channels.get(&channel_id).is_some(), "Channel not found");
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can set minter allowances"
+        );
+
+        let minter_allowance = MinterAllowance {
+            remaining: allowance,
+            expires_at,
+            receivers,
+        };
+        self.minter_allowances.insert(&(self.channel_scope(&channel_id), account_id.clone()), &minter_allowance);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"minter_allowance_set\",\"data\":{{\"channel_id\":\"{}\",\"account_id\":\"{}\",\"allowance\":{}}}}}",
+            channel_id,
+            account_id,
+            serde_json::to_string(&minter_allowance).unwrap()
+        ));
+    }
+
+    pub fn revoke_minter_allowance(&mut self, channel_id: String, account_id: AccountId) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can revoke minter allowances"
+        );
+
+        if self.minter_allowances.remove(&(self.channel_scope(&channel_id), account_id.clone())).is_some() {
+            env::log_str(&format!(
+                "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"minter_allowance_revoked\",\"data\":{{\"channel_id\":\"{}\",\"account_id\":\"{}\"}}}}",
+                channel_id,
+                account_id
+            ));
+        }
+    }
+
+    pub fn get_minter_allowance(&self, channel_id: String, account_id: AccountId) -> Option<MinterAllowance> {
+        self.minter_allowances.get(&(self.channel_scope(&channel_id), account_id))
+    }
+
+    pub fn pause(&mut self) {
+        self.assert_can_pause();
+        self.paused = true;
+        self.log_pause_event("paused", None);
+    }
+
+    pub fn unpause(&mut self) {
+        self.assert_can_pause();
+        self.paused = false;
+        self.log_pause_event("unpaused", None);
+    }
+
+    pub fn pause_channel(&mut self, channel_id: String) {
+        self.assert_can_pause();
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        channel.paused = true;
+        self.channels.insert(&channel_id, &channel);
+        self.log_pause_event("paused", Some(&channel_id));
+    }
+
+    pub fn unpause_channel(&mut self, channel_id: String) {
+        self.assert_can_pause();
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        channel.paused = false;
+        self.channels.insert(&channel_id, &channel);
+        self.log_pause_event("unpaused", Some(&channel_id));
+    }
+
+    pub fn archive_channel(&mut self, channel_id: String) {
+        assert!(
+            self.can_manage_channel(&channel_id, &env::predecessor_account_id()),
+            "Only the owner, an admin or a channel manager can archive channels"
+        );
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        channel.archived = true;
+        self.channels.insert(&channel_id, &channel);
+
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"archive_series\",\"data\":{{\"series_id\":\"{}\"}}}}",
+            channel_id
+        ));
+    }
+
+    // Drains the channel's state while gas allows and refunds the freed storage to the owner.
+    // Returns true once the channel is fully removed, otherwise call again to continue.
+    pub fn delete_channel(&mut self, channel_id: String) -> bool {
+        assert!(
+            self.is_admin(&env::predecessor_account_id()),
+            "Only the owner or an admin can delete channels"
+        );
+        let initial_storage = env::storage_usage();
+
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert_eq!(channel.total_supply, 0, "Channel still has tokens");
+        assert!(
+            self.storage_pools.get(&channel_id).is_none_or(|pool| pool.balance.is_zero()),
+            "Channel storage pool must be withdrawn first"
+        );
+        assert!(
+            self.channel_proceeds.get(&channel_id).is_none()
+                && channel.auction.as_ref().is_none_or(|auction| auction.escrow.is_zero())
+                && self.channel_ft_proceeds_tokens.get(&channel_id).is_none(),
+            "Channel proceeds must be withdrawn first"
+        );
+
+        // Archive first so nothing is minted between paged calls
+        channel.archived = true;
+        let mut done = Self::drain_set(&mut channel.minted_tokens);
+        if done {
+            done = Self::drain_map(&mut channel.random_swaps);
+        }
+
+        self.metadata_update_jobs.remove(&channel_id);
+        for role in [ChannelRole::ChannelManager, ChannelRole::Minter, ChannelRole::MetadataEditor, ChannelRole::StateUpdater] {
+            if !done {
+                break;
+            }
+            let key = (channel_id.clone(), role);
+            if let Some(mut members) = self.channel_roles.get(&key) {
+                done = Self::drain_set(&mut members);
+                if done {
+                    self.channel_roles.remove(&key);
+                } else {
+                    self.channel_roles.insert(&key, &members);
+                }
+            }
+        }
+
+        if done {
+            self.channels.remove(&channel_id);
+            self.storage_pools.remove(&channel_id);
+            // Allowances, referrals, sponsored mints, voucher nonces and claim
+            // keys of this channel stay behind under the old generation
+            let generation = self.channel_generations.get(&channel_id).unwrap_or(0);
+            self.channel_generations.insert(&channel_id, &(generation + 1));
+            env::log_str(&format!(
+                "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"delete_series\",\"data\":{{\"series_id\":\"{}\"}}}}",
+                channel_id
+            ));
+        } else {
+            self.channels.insert(&channel_id, &channel);
+        }
+
+        // Refund the released storage
+        let freed_storage = initial_storage.saturating_sub(env::storage_usage());
+        if freed_storage > 0 {
+            let refund = env::storage_byte_cost().saturating_mul(freed_storage as u128);
+            let _ = Promise::new(self.owner_id.clone()).transfer(refund);
+        }
+
+        done
+    }
+
+    pub fn get_pause_status(&self, channel_id: Option<String>) -> PauseStatus {
+        PauseStatus {
+            contract_paused: self.paused,
+            channel_paused: channel_id
+                .and_then(|channel_id| self.channels.get(&channel_id))
+                .map(|channel| channel.paused),
+        }
+    }
+
+    pub fn has_role(&self, role: ContractRole, account_id: AccountId) -> bool {
+        self.account_has_role(role, &account_id)
+    }
+
+    pub fn has_channel_role(&self, channel_id: String, role: ChannelRole, account_id: AccountId) -> bool {
+        self.account_has_channel_role(&channel_id, role, &account_id)
+    }
+
+    pub fn get_role_members(
+        &self,
+        role: ContractRole,
+        from_index: Option<U128>,
+        limit: Option<u64>
+    ) -> Vec<AccountId> {
+        let start = u128::from(from_index.unwrap_or(U128(0))) as usize;
+        let limit = limit.unwrap_or(50) as usize;
+
+        self.roles
+            .get(&role)
+            .map(|members| members.iter().skip(start).take(limit).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn get_channel_role_members(
+        &self,
+        channel_id: String,
+        role: ChannelRole,
+        from_index: Option<U128>,
+        limit: Option<u64>
+    ) -> Vec<AccountId> {
+        let start = u128::from(from_index.unwrap_or(U128(0))) as usize;
+        let limit = limit.unwrap_or(50) as usize;
+
+        self.channel_roles
+            .get(&(channel_id, role))
+            .map(|members| members.iter().skip(start).take(limit).collect())
+            .unwrap_or_default()
+    }
+
+    fn account_has_role(&self, role: ContractRole, account_id: &AccountId) -> bool {
+        self.roles
+            .get(&role)
+            .map(|members| members.contains(account_id))
+            .unwrap_or(false)
+    }
+
+    fn account_has_channel_role(&self, channel_id: &str, role: ChannelRole, account_id: &AccountId) -> bool {
+        self.channel_roles
+            .get(&(channel_id.to_string(), role))
+            .map(|members| members.contains(account_id))
+            .unwrap_or(false)
+    }
+
+    fn is_admin(&self, account_id: &AccountId) -> bool {
+        *account_id == self.owner_id || self.account_has_role(ContractRole::Admin, account_id)
+    }
+
+    fn can_manage_channel(&self, channel_id: &str, account_id: &AccountId) -> bool {
+        self.is_admin(account_id)
+            || self.account_has_channel_role(channel_id, ChannelRole::ChannelManager, account_id)
+    }
+
+    // Key for per-account channel state kept outside `Channel`. `delete_channel` bumps the
+    // generation, so a channel recreated under the same id doesn't inherit the old entries.
+    fn channel_scope(&self, channel_id: &str) -> String {
+        match self.channel_generations.get(&channel_id.to_string()) {
+            Some(generation) => format!("{}:{}", channel_id, generation),
+            None => channel_id.to_string(),
+        }
+    }
+
+    // Removes elements until the set is empty or the gas reserve is reached
+    fn drain_set<T: BorshSerialize + BorshDeserialize>(set: &mut UnorderedSet<T>) -> bool {
+        while !set.is_empty() {
+            if env::prepaid_gas().saturating_sub(env::used_gas()) < PAGED_JOB_GAS_RESERVE {
+                return false;
+            }
+            let last = set.as_vector().get(set.len() - 1).unwrap();
+            set.remove(&last);
+        }
+        true
+    }
+
+    fn drain_map<K, V>(map: &mut UnorderedMap<K, V>) -> bool
+    where
+        K: BorshSerialize + BorshDeserialize,
+        V: BorshSerialize + BorshDeserialize,
+    {
+        while !map.is_empty() {
+            if env::prepaid_gas().saturating_sub(env::used_gas()) < PAGED_JOB_GAS_RESERVE {
+                return false;
+            }
+            let last = map.keys_as_vector().get(map.len() - 1).unwrap();
+            map.remove(&last);
+        }
+        true
+    }
+
+    fn assert_can_pause(&self) {
+        let caller = env::predecessor_account_id();
+        assert!(
+            self.is_admin(&caller) || self.account_has_role(ContractRole::Pauser, &caller),
+            "Only the owner, an admin or a pauser can change the pause state"
+        );
+    }
+
+    fn assert_not_paused(&self, channel: &Channel) {
+        assert!(!self.paused, "Contract is paused");
+        assert!(!channel.paused, "Channel is paused");
+    }
+
+    fn assert_token_not_paused(&self, token_id: &str) {
+        assert!(!self.paused, "Contract is paused");
+        if let Some((channel_id, _)) = token_id.split_once(':') {
+            if let Some(channel) = self.channels.get(&channel_id.to_string()) {
+                assert!(!channel.paused, "Channel is paused");
+            }
+        }
+    }
+
+    fn log_pause_event(&self, event: &str, channel_id: Option<&str>) {
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"{}\",\"data\":{{\"channel_id\":{},\"sender_id\":\"{}\"}}}}",
+            event,
+            serde_json::to_string(&channel_id).unwrap(),
+            env::predecessor_account_id()
+        ));
+    }
+
+    // Returns false when the minter holds no allowance for the channel, otherwise consumes one mint
+    // Expired, exhausted or receiver-restricted allowances don't apply, the mint falls back to a proof
+    fn use_minter_allowance(&mut self, channel_id: &str, minter_id: &AccountId, receiver_id: &AccountId) -> bool {
+        let key = (self.channel_scope(channel_id), minter_id.clone());
+        let mut allowance = match self.minter_allowances.get(&key) {
+            Some(allowance) => allowance,
+            None => return false,
+        };
+
+        if allowance.expires_at.is_some_and(|expires_at| env::block_timestamp() >= expires_at)
+            || allowance.receivers.as_ref().is_some_and(|receivers| !receivers.contains(receiver_id))
+            || allowance.remaining == 0
+        {
+            return false;
+        }
+
+        allowance.remaining -= 1;
+        self.minter_allowances.insert(&key, &allowance);
+        true
+    }
+
+    fn assert_can_administer_channel_role(&self, channel_id: &str, role: ChannelRole, account_id: &AccountId) {
+        // Managers can hand out Minter and MetadataEditor, only admins can appoint managers
+        if role == ChannelRole::ChannelManager {
+            assert!(self.is_admin(account_id), "Only the owner or an admin can administer channel managers");
+        } else {
+            assert!(
+                self.can_manage_channel(channel_id, account_id),
+                "Only the owner, an admin or a channel manager can administer channel roles"
+            );
+        }
+    }
+
+    fn add_channel_role_member(&mut self, channel_id: &str, role: ChannelRole, account_id: &AccountId) -> bool {
+        let key = (channel_id.to_string(), role);
+        let mut members = self.channel_roles
+            .get(&key)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKey::ChannelRoleMembers {
+                channel_id: channel_id.to_string(),
+                role,
+            }));
+        let added = members.insert(account_id);
+        if added {
+            self.channel_roles.insert(&key, &members);
+        }
+        added
+    }
+
+    fn log_role_event<R: Serialize>(
+        &self,
+        event: &str,
+        role: &R,
+        channel_id: Option<&str>,
+        account_id: &AccountId,
+        sender_id: &AccountId,
+    ) {
+        env::log_str(&format!(
+            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"{}\",\"data\":{{\"role\":{},\"channel_id\":{},\"account_id\":\"{}\",\"sender_id\":\"{}\"}}}}",
+            event,
+            serde_json::to_string(role).unwrap(),
+            serde_json::to_string(&channel_id).unwrap(),
+            account_id,
+            sender_id
+        ));
+    }
+}
+
+// Values the placeholders of channel templates render from
+pub struct TemplateContext<'a> {
+    pub number: u64,
+    pub item: u64,
+    pub channel: &'a str,
+    pub tier: Option<&'a str>,
+    pub max_supply: Option<u64>,
+    pub minted_at: Option<u64>,
+    pub owner: Option<AccountId>,
+    pub state: Option<&'a TokenState>,
+}
+
+const MAX_TEMPLATE_PADDING: usize = 20;
+
+// Placeholders: `{}` and `{number}` for the token number, `{item}` for the item number after a
+// reveal, `{number:04}`/`{item:04}` zero-padded to the given width, `{channel}`, `{tier}`,
+// `{max_supply}`, `{minted_date}` as YYYY-MM-DD, `{owner}`, `{level}` and `{attribute.<key>}`
+fn render_template(template: &str, context: &TemplateContext) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let placeholder = &rest[start + 1..start + end];
+        match render_placeholder(placeholder, context) {
+            Some(value) => rendered.push_str(&value),
+            // Templates stored before validation may hold other braces, keep them as written
+            None => rendered.push_str(&rest[start..=start + end]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn render_placeholder(placeholder: &str, context: &TemplateContext) -> Option<String> {
+    let (name, padding) = match placeholder.split_once(':') {
+        Some((name, format)) => (name, Some(parse_padding(format)?)),
+        None => (placeholder, None),
+    };
+    let value = match name {
+        "" | "number" => context.number.to_string(),
+        "item" => context.item.to_string(),
+        "channel" if padding.is_none() => context.channel.to_string(),
+        "tier" if padding.is_none() => context.tier.unwrap_or_default().to_string(),
+        "max_supply" if padding.is_none() => context.max_supply.map_or_else(|| "unlimited".to_string(), |supply| supply.to_string()),
+        "minted_date" if padding.is_none() => context.minted_at
+            .and_then(|minted_at| time::OffsetDateTime::from_unix_timestamp_nanos(minted_at as i128).ok())
+            .map(|minted_at| {
+                let date = minted_at.date();
+                format!("{:04}-{:02}-{:02}", date.year(), u8::from(date.month()), date.day())
+            })
+            .unwrap_or_default(),
+        "owner" if padding.is_none() => context.owner.as_ref().map(|owner| owner.to_string()).unwrap_or_default(),
+        "level" => context.state.map_or(0, |state| state.level).to_string(),
+        _ if padding.is_none() && name.strip_prefix("attribute.").is_some_and(|key| !key.is_empty()) => context.state
+            .and_then(|state| state.attributes.get(&name["attribute.".len()..]).cloned())
+            .unwrap_or_default(),
+        _ => return None,
+    };
+    Some(match padding {
+        Some(width) => format!("{:0>width$}", value, width = width),
+        None => value,
+    })
+}
+
+// `04` pads to four digits
+fn parse_padding(format: &str) -> Option<usize> {
+    let width = format.strip_prefix('0')?.parse::<usize>().ok()?;
+    (width > 0 && width <= MAX_TEMPLATE_PADDING).then_some(width)
+}
+
+fn validate_template(template: &str) {
+    let context = TemplateContext {
+        number: 1,
+        item: 1,
+        channel: "",
+        tier: None,
+        max_supply: None,
+        minted_at: None,
+        owner: None,
+        state: None,
+    };
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').unwrap_or_else(|| env::panic_str("Unclosed template placeholder"));
+        let placeholder = &rest[start + 1..start + end];
+        assert!(
+            render_placeholder(placeholder, &context).is_some(),
+            "Unknown template placeholder {{{}}}",
+            placeholder
+        );
+        rest = &rest[start + end + 1..];
+    }
+}
+
+fn validate_channel_metadata(metadata: &ChannelMetadata) {
+    validate_template(&metadata.title_template);
+    validate_template(&metadata.description_template);
+    validate_template(&metadata.media);
+    validate_template(&metadata.reference);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, AccountId};
+
+    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(predecessor_account_id.clone())
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    #[test]
+    fn test_new() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new(accounts(1));
+        assert_eq!(contract.owner_id, accounts(1));
+    }
+
+    #[test]
+    fn test_create_channel() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],  // Example merkle root
+            metadata.clone(),
+            None
+        );
+
+        let channel = contract.get_channel_info("test_channel".to_string()).unwrap();
+        assert_eq!(channel.total_supply, 0);
+        assert_eq!(channel.next_token_number, 1);
+        assert_eq!(channel.metadata.title_template, "Test Channel #{}");
+    }
+
+    #[test]
+    fn test_mint_and_transfer() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        // Create channel
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+
+        // Mint token
+        let token_id = contract.nft_mint(
+            "test_channel".to_string(),
+            None,  // First token doesn't need proof
+            accounts(2),
+            None
+        );
+
+        // Check ownership
+        let owner_tokens = contract.nft_tokens_for_owner(accounts(2), None, None);
+        assert_eq!(owner_tokens.len(), 1);
+        assert_eq!(owner_tokens[0].token_id, token_id);
+
+        // Transfer token
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.nft_transfer(accounts(3), token_id.clone(), None, None);
+
+        // Check new ownership
+        let new_owner_tokens = contract.nft_tokens_for_owner(accounts(3), None, None);
+        assert_eq!(new_owner_tokens.len(), 1);
+        assert_eq!(new_owner_tokens[0].token_id, token_id);
+    }
+
+    #[test]
+    fn test_burn() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        // Create channel and mint token
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+
+        let token_id = contract.nft_mint(
+            "test_channel".to_string(),
+            None,
+            accounts(2),
+            None
+        );
+
+        // Burn token
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.nft_burn(token_id.clone());
+
+        // Verify token is burned
+        assert!(!contract.is_minted(token_id));
+        let owner_tokens = contract.nft_tokens_for_owner(accounts(2), None, None);
+        assert_eq!(owner_tokens.len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner, an admin or a channel creator can create channels")]
+    fn test_create_channel_not_owner() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+    }
+
+    #[test]
+    fn test_channel_creator_role() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        contract.grant_role(ContractRole::ChannelCreator, accounts(2));
+        assert!(contract.has_role(ContractRole::ChannelCreator, accounts(2)));
+        assert_eq!(contract.get_role_members(ContractRole::ChannelCreator, None, None), vec![accounts(2)]);
+
+        // Creator creates a channel and becomes its manager
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .build());
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        assert!(contract.has_channel_role("test_channel".to_string(), ChannelRole::ChannelManager, accounts(2)));
+
+        // Manager can appoint a metadata editor and update the root
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::MetadataEditor, accounts(3));
+        contract.update_merkle_root("test_channel".to_string(), vec![4, 5, 6]);
+        assert_eq!(contract.get_channel_info("test_channel".to_string()).unwrap().merkle_root, vec![4, 5, 6]);
+        assert_eq!(
+            contract.get_channel_role_members("test_channel".to_string(), ChannelRole::MetadataEditor, None, None),
+            vec![accounts(3)]
+        );
+
+        // Revoked creators can no longer create channels
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.revoke_role(ContractRole::ChannelCreator, accounts(2));
+        assert!(!contract.has_role(ContractRole::ChannelCreator, accounts(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner, an admin or a channel manager can update the Merkle root")]
+    fn test_metadata_editor_cannot_update_root() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::MetadataEditor, accounts(2));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.update_channel("test_channel".to_string(), Some(vec![4, 5, 6]), None);
+    }
+
+    #[test]
+    fn test_minter_allowance() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_minter_allowance("test_channel".to_string(), accounts(2), 2, None, Some(vec![accounts(3)]));
+
+        // Partner mints twice without proofs
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(3), None);
+        contract.nft_mint("test_channel".to_string(), None, accounts(3), None);
+
+        let allowance = contract.get_minter_allowance("test_channel".to_string(), accounts(2)).unwrap();
+        assert_eq!(allowance.remaining, 0);
+        assert_eq!(contract.nft_supply_for_owner(accounts(3)), U128(2));
+    }
+
+    #[test]
+    fn test_minter_allowance_wrong_receiver() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            env::sha256(b"test_channel:2"),
+            metadata,
+            None
+        );
+        contract.set_minter_allowance("test_channel".to_string(), accounts(2), 5, None, Some(vec![accounts(3)]));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(3), None);
+
+        // Other receivers are not covered, so the mint needs a proof and leaves the quota alone
+        contract.nft_mint("test_channel".to_string(), Some(vec![]), accounts(4), None);
+        let allowance = contract.get_minter_allowance("test_channel".to_string(), accounts(2)).unwrap();
+        assert_eq!(allowance.remaining, 4);
+        assert_eq!(contract.nft_supply_for_owner(accounts(4)), U128(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Proof required for minting")]
+    fn test_minter_allowance_exhausted() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_minter_allowance("test_channel".to_string(), accounts(2), 1, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(3), None);
+        contract.nft_mint("test_channel".to_string(), None, accounts(3), None);
+    }
+
+    #[test]
+    fn test_pause_by_pauser() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.grant_role(ContractRole::Pauser, accounts(2));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.pause();
+        assert!(contract.get_pause_status(None).contract_paused);
+
+        contract.unpause();
+        assert!(!contract.get_pause_status(None).contract_paused);
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel is paused")]
+    fn test_mint_paused_channel() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.pause_channel("test_channel".to_string());
+        assert_eq!(contract.get_pause_status(Some("test_channel".to_string())).channel_paused, Some(true));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_withdraw_payee_balance_paused() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.pause();
+        let _ = contract.withdraw_payee_balance();
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_claim_auction_rebate_paused() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.pause();
+        let _ = contract.claim_auction_rebate(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_commit_mint_paused() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(NearToken::from_near(1)).build());
+        let mut contract = Contract::new(accounts(1));
+        contract.pause();
+        contract.commit_mint(Base64VecU8(vec![0; 32]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel is paused")]
+    fn test_update_token_state_paused() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(NearToken::from_near(1)).build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        let token_id = contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+        contract.pause_channel("test_channel".to_string());
+
+        contract.update_token_state(token_id, Some(5), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel is paused")]
+    fn test_token_metadata_override_paused() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(NearToken::from_near(1)).build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        let token_id = contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+        contract.pause_channel("test_channel".to_string());
+
+        contract.set_token_metadata_override(token_id, TokenMetadataOverride {
+            title: Some("One-off".to_string()),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel is paused")]
+    fn test_add_claim_keys_paused() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.pause_channel("test_channel".to_string());
+
+        let public_key: PublicKey = "ed25519:GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB".parse().unwrap();
+        contract.add_claim_keys("test_channel".to_string(), vec![public_key]);
+    }
+
+    #[test]
+    fn test_delete_channel() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata.clone(),
+            None
+        );
+        contract.set_minter_allowance("test_channel".to_string(), accounts(2), 5, None, None);
+        let token_id = contract.nft_mint("test_channel".to_string(), None, accounts(1), None);
+        contract.nft_burn(token_id);
+
+        assert!(contract.delete_channel("test_channel".to_string()));
+        assert!(contract.get_channel_info("test_channel".to_string()).is_none());
+
+        // A channel recreated under the same id starts without the old channel's allowances
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        assert!(contract.get_minter_allowance("test_channel".to_string(), accounts(2)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel proceeds must be withdrawn first")]
+    fn test_delete_channel_with_ft_proceeds() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_ft_price("test_channel".to_string(), Some(FtPrice {
+            token_id: accounts(3),
+            amount: U128(100),
+        }));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.fund_storage_pool("test_channel".to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        let _ = contract.ft_on_transfer(
+            accounts(1),
+            U128(100),
+            r#"{"channel_id":"test_channel"}"#.to_string()
+        );
+
+        // Proceeds in a token the channel is no longer priced in still block the deletion
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        contract.set_ft_price("test_channel".to_string(), None);
+        contract.nft_burn("test_channel:1".to_string());
+        let _ = contract.withdraw_storage_pool("test_channel".to_string(), None);
+        contract.delete_channel("test_channel".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel is archived")]
+    fn test_mint_archived_channel() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.archive_channel("test_channel".to_string());
+        assert!(contract.get_channel_info("test_channel".to_string()).unwrap().archived);
+
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+    }
+
+    #[test]
+    fn test_freeze_merkle_root() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata.clone(),
+            None
+        );
+        contract.freeze_merkle_root("test_channel".to_string());
+
+        // Metadata stays editable while only the root is frozen
+        let mut new_metadata = metadata;
+        new_metadata.media = "https://example.com/new.jpg".to_string();
+        contract.update_channel("test_channel".to_string(), None, Some(new_metadata));
+
+        let channel = contract.get_channel_info("test_channel".to_string()).unwrap();
+        assert!(channel.root_frozen);
+        assert!(!channel.metadata_frozen);
+        assert!(!channel.frozen);
+        assert_eq!(channel.metadata.media, "https://example.com/new.jpg");
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel metadata is frozen")]
+    fn test_update_frozen_channel() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata.clone(),
+            None
+        );
+        contract.freeze_channel("test_channel".to_string());
+        assert!(contract.get_channel_info("test_channel".to_string()).unwrap().frozen);
+
+        contract.update_channel("test_channel".to_string(), None, Some(metadata));
+    }
+
+    #[test]
+    fn test_paid_mint() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_mint_price("test_channel".to_string(), NearToken::from_near(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_near(2))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+        assert_eq!(contract.get_channel_proceeds("test_channel".to_string()), NearToken::from_near(1));
+        assert_eq!(contract.get_treasury().total_proceeds, NearToken::from_near(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        let _ = contract.withdraw_channel_proceeds("test_channel".to_string(), None);
+        assert!(contract.get_channel_proceeds("test_channel".to_string()).is_zero());
+        assert!(contract.get_treasury().total_proceeds.is_zero());
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover the mint price and storage")]
+    fn test_paid_mint_insufficient_deposit() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_mint_price("test_channel".to_string(), NearToken::from_near(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+    }
+
+    #[test]
+    fn test_ft_mint() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_ft_price("test_channel".to_string(), Some(FtPrice {
+            token_id: accounts(3),
+            amount: U128(100),
+        }));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.fund_storage_pool("test_channel".to_string());
+
+        // The token contract forwards the transfer
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        let unused = contract.ft_on_transfer(
+            accounts(2),
+            U128(150),
+            r#"{"channel_id":"test_channel"}"#.to_string()
+        );
+        match unused {
+            PromiseOrValue::Value(unused) => assert_eq!(unused, U128(50)),
+            _ => panic!("Expected unused tokens to be returned"),
+        }
+
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128(1));
+        assert_eq!(contract.get_channel_ft_proceeds("test_channel".to_string(), accounts(3)), U128(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel is not priced in this token")]
+    fn test_ft_mint_wrong_token() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_ft_price("test_channel".to_string(), Some(FtPrice {
+            token_id: accounts(3),
+            amount: U128(100),
+        }));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .build());
+        let unused = contract.ft_on_transfer(
+            accounts(2),
+            U128(100),
+            r#"{"channel_id":"test_channel"}"#.to_string()
+        );
+        assert!(matches!(unused, PromiseOrValue::Value(U128(0))));
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel is priced in a fungible token")]
+    fn test_near_mint_on_ft_priced_channel() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_ft_price("test_channel".to_string(), Some(FtPrice {
+            token_id: accounts(3),
+            amount: U128(100),
+        }));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+    }
+
+    #[test]
+    fn test_bonding_curve_price() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+
+        contract.set_pricing_curve("test_channel".to_string(), Some(PricingCurve::Linear {
+            base_price: NearToken::from_near(1),
+            increment: NearToken::from_millinear(100),
+        }));
+        assert_eq!(contract.get_mint_price("test_channel".to_string(), 2), NearToken::from_millinear(2100));
+
+        // Doubling price per token already minted
+        contract.set_pricing_curve("test_channel".to_string(), Some(PricingCurve::Exponential {
+            base_price: NearToken::from_near(1),
+            growth_bps: 10_000,
+        }));
+        assert_eq!(contract.get_mint_price("test_channel".to_string(), 3), NearToken::from_near(7));
+    }
+
+    #[test]
+    fn test_exponential_price_past_overflow() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(1));
+        contract.set_pricing_curve("test_channel".to_string(), Some(PricingCurve::Exponential {
+            base_price: NearToken::from_yoctonear(1),
+            growth_bps: 1_000,
+        }));
+
+        // The multiplier passes 340x at 62 tokens, minting keeps working beyond that
+        for _ in 0..70 {
+            contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+        }
+        assert_eq!(contract.get_mint_price("test_channel".to_string(), 1), NearToken::from_yoctonear(789));
+
+        // Prices that no longer fit in a u128 are capped
+        assert_eq!(
+            Contract::exponential_price(NearToken::from_near(1), 1_000, 10_000),
+            NearToken::from_yoctonear(u128::MAX)
+        );
+    }
+
+    #[test]
+    fn test_dutch_auction_rebate() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_pricing_curve("test_channel".to_string(), Some(PricingCurve::DutchAuction {
+            start_price: NearToken::from_near(10),
+            floor_price: NearToken::from_near(2),
+            start_time: 0,
+            duration: 100,
+        }));
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(3));
+
+        // Halfway through the price has decayed to 6 NEAR
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(50)
+            .attached_deposit(NearToken::from_near(7))
+            .build());
+        assert_eq!(contract.get_mint_price("test_channel".to_string(), 1), NearToken::from_near(6));
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(75)
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(3), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        assert_eq!(contract.settle_dutch_auction("test_channel".to_string()), NearToken::from_near(4));
+        assert_eq!(contract.get_channel_proceeds("test_channel".to_string()), NearToken::from_near(8));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .build());
+        let round = contract.get_channel_info("test_channel".to_string()).unwrap().auction.unwrap().round;
+        assert_eq!(contract.get_settled_auction(round).unwrap().escrow, NearToken::from_near(2));
+
+        // A new round can start while the rebate of the earlier one is still unclaimed
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.set_pricing_curve("test_channel".to_string(), Some(PricingCurve::DutchAuction {
+            start_price: NearToken::from_near(10),
+            floor_price: NearToken::from_near(2),
+            start_time: 100,
+            duration: 100,
+        }));
+        assert_eq!(contract.get_channel_info("test_channel".to_string()).unwrap().auction.unwrap().round, round + 1);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .build());
+        let _ = contract.claim_auction_rebate(round);
+        assert!(contract.get_settled_auction(round).is_none());
+        assert!(contract.get_auction_payment(round, accounts(2)).is_none());
+    }
+
+    #[test]
+    fn test_revenue_splits() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_mint_price("test_channel".to_string(), NearToken::from_near(1));
+        contract.set_revenue_splits("test_channel".to_string(), vec![
+            RevenueSplit { account_id: accounts(3), bps: 5_000 },
+            RevenueSplit { account_id: accounts(4), bps: 3_000 },
+        ]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_near(2))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+
+        assert_eq!(contract.get_payee_balance(accounts(3)), NearToken::from_millinear(500));
+        assert_eq!(contract.get_payee_balance(accounts(4)), NearToken::from_millinear(300));
+        assert_eq!(contract.get_channel_proceeds("test_channel".to_string()), NearToken::from_millinear(200));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        let _ = contract.withdraw_payee_balance();
+        assert!(contract.get_payee_balance(accounts(3)).is_zero());
+    }
+
+    #[test]
+    #[should_panic(expected = "Revenue splits are frozen")]
+    fn test_frozen_revenue_splits() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_revenue_splits("test_channel".to_string(), vec![
+            RevenueSplit { account_id: accounts(3), bps: 5_000 },
+        ]);
+        contract.freeze_revenue_splits("test_channel".to_string());
+
+        contract.set_revenue_splits("test_channel".to_string(), vec![]);
+    }
+
+    #[test]
+    fn test_referral_reward() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_mint_price("test_channel".to_string(), NearToken::from_near(1));
+        contract.set_referral_rate("test_channel".to_string(), 1_000);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_near(2))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), Some(MintOptions { referrer_id: Some(accounts(3)), ..Default::default() }));
+
+        assert_eq!(contract.get_payee_balance(accounts(3)), NearToken::from_millinear(100));
+        assert_eq!(contract.get_referral_count("test_channel".to_string(), accounts(3)), 1);
+        assert_eq!(contract.get_channel_proceeds("test_channel".to_string()), NearToken::from_millinear(900));
+    }
+
+    #[test]
+    #[should_panic(expected = "Self-referral is not allowed")]
+    fn test_self_referral() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(3), Some(MintOptions { referrer_id: Some(accounts(2)), ..Default::default() }));
+    }
+
+    #[test]
+    fn test_sponsored_mint() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_sponsorship_limit("test_channel".to_string(), Some(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.fund_storage_pool("test_channel".to_string());
+
+        // A user with an empty wallet mints for free
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+
+        let pool = contract.get_storage_pool("test_channel".to_string()).unwrap();
+        assert!(pool.balance < NearToken::from_near(1));
+        assert!(pool.estimated_remaining_mints > 0);
+        assert_eq!(contract.get_sponsored_mints("test_channel".to_string(), accounts(2)), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover the mint price and storage")]
+    fn test_sponsored_mint_limit() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.fund_storage_pool("test_channel".to_string());
+        contract.set_sponsorship_limit("test_channel".to_string(), Some(1));
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(2));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+    }
+
+    #[test]
+    fn test_mint_with_voucher() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.add_voucher_signer(
+            "test_channel".to_string(),
+            "ed25519:GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB".parse().unwrap()
+        );
+
+        // Payload signed off-chain with the signer's secret key
+        let payload = r#"{"contract_id":"alice","channel_id":"test_channel","receiver_id":"charlie","token_number":null,"quantity":2,"nonce":1,"expires_at":1000}"#;
+        let signature: Base64VecU8 = serde_json::from_str(
+            r#""iUvqGNW2swlfNVUi3Zcp3zVWTQjma2G7xa+AqzJC6bhxPtUdHqC53MKMOTQ3j72H92OqhcUmTnJKGhcl4CSjDw==""#
+        ).unwrap();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let token_ids = contract.nft_mint_with_voucher(payload.to_string(), signature);
+
+        assert_eq!(token_ids, vec!["test_channel:1".to_string(), "test_channel:2".to_string()]);
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128(2));
+        assert!(contract.is_voucher_nonce_used("test_channel".to_string(), 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid voucher signature")]
+    fn test_mint_with_forged_voucher() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.add_voucher_signer(
+            "test_channel".to_string(),
+            "ed25519:GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB".parse().unwrap()
+        );
+
+        // Signature over a payload with a different quantity
+        let payload = r#"{"contract_id":"alice","channel_id":"test_channel","receiver_id":"charlie","token_number":null,"quantity":20,"nonce":1,"expires_at":1000}"#;
+        let signature: Base64VecU8 = serde_json::from_str(
+            r#""iUvqGNW2swlfNVUi3Zcp3zVWTQjma2G7xa+AqzJC6bhxPtUdHqC53MKMOTQ3j72H92OqhcUmTnJKGhcl4CSjDw==""#
+        ).unwrap();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.nft_mint_with_voucher(payload.to_string(), signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "Voucher is for another contract")]
+    fn test_mint_with_voucher_for_other_contract() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.add_voucher_signer(
+            "test_channel".to_string(),
+            "ed25519:GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB".parse().unwrap()
+        );
+
+        // Correctly signed, but for a deployment at another account
+        let payload = r#"{"contract_id":"bob","channel_id":"test_channel","receiver_id":"charlie","token_number":null,"quantity":2,"nonce":1,"expires_at":1000}"#;
+        let signature: Base64VecU8 = serde_json::from_str(
+            r#""ytyKNQm3eCn+fSMtKx3xiUML90MYPWlreoebgNV/hh9l0KWo8IgjX7S/XiwAy4CD7RFIYDPGFiAY5HBNwak/DA==""#
+        ).unwrap();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.nft_mint_with_voucher(payload.to_string(), signature);
+    }
+
+    #[test]
+    fn test_claim_with_key() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        let public_key: PublicKey = "ed25519:GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB".parse().unwrap();
+        contract.add_claim_keys("test_channel".to_string(), vec![public_key.clone()]);
+        assert_eq!(contract.get_claim_key(public_key.clone()).unwrap().depositor_id, accounts(1));
+
+        // The claim key signs a call from the contract to itself
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .signer_account_id(accounts(0))
+            .signer_account_pk(public_key.clone())
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        let token_id = contract.claim(accounts(2));
+
+        assert_eq!(token_id, "test_channel:1");
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128(1));
+        assert!(contract.get_claim_key(public_key).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Claims must be signed by a claim key")]
+    fn test_claim_from_other_account() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        let public_key: PublicKey = "ed25519:GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB".parse().unwrap();
+        contract.add_claim_keys("test_channel".to_string(), vec![public_key.clone()]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .signer_account_pk(public_key)
+            .build());
+        contract.claim(accounts(2));
+    }
+
+    #[test]
+    fn test_claim_pending_mint() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        let secret_hash = Base64VecU8(env::sha256(b"emailed-secret"));
+        let token_id = contract.nft_mint_pending("test_channel".to_string(), secret_hash.clone(), None);
+        assert_eq!(contract.nft_supply_for_owner(accounts(0)), U128(1));
+        assert_eq!(contract.get_pending_mints("test_channel".to_string(), None, None).len(), 1);
+
+        let commitment = env::sha256(borsh::to_vec(&("emailed-secret".to_string(), accounts(3))).unwrap());
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_millinear(10))
+            .block_height(10)
+            .build());
+        contract.commit_mint(Base64VecU8(commitment.clone()));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(11)
+            .build());
+        assert_eq!(contract.claim_pending("emailed-secret".to_string(), accounts(3)), token_id);
+        assert_eq!(contract.nft_supply_for_owner(accounts(3)), U128(1));
+        assert_eq!(contract.nft_supply_for_owner(accounts(0)), U128(0));
+        assert!(contract.get_pending_mint(secret_hash).is_none());
+        assert!(contract.get_mint_commitment(Base64VecU8(commitment)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "No matching commitment")]
+    fn test_claim_pending_for_other_receiver() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        let secret_hash = Base64VecU8(env::sha256(b"emailed-secret"));
+        contract.nft_mint_pending("test_channel".to_string(), secret_hash, None);
+
+        // The receiver committed first, a copied secret can't redirect the token
+        let commitment = env::sha256(borsh::to_vec(&("emailed-secret".to_string(), accounts(3))).unwrap());
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_millinear(10))
+            .block_height(10)
+            .build());
+        contract.commit_mint(Base64VecU8(commitment));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(11)
+            .build());
+        contract.claim_pending("emailed-secret".to_string(), accounts(4));
+    }
+
+    #[test]
+    fn test_expire_pending_mint() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_pending_mint_policy("test_channel".to_string(), 100, false);
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(1));
+        let secret_hash = Base64VecU8(env::sha256(b"first-secret"));
+        let token_id = contract.nft_mint_pending("test_channel".to_string(), secret_hash.clone(), None);
+
+        // The expired token is recycled into the next pending mint
+        testing_env!(context
+            .block_timestamp(100)
+            .build());
+        contract.expire_pending_mint(secret_hash);
+        let next_hash = Base64VecU8(env::sha256(b"second-secret"));
+        assert_eq!(contract.nft_mint_pending("test_channel".to_string(), next_hash, None), token_id);
+        assert_eq!(contract.get_channel_info("test_channel".to_string()).unwrap().total_supply, 1);
+
+        // Burned once the channel stops recycling
+        contract.set_pending_mint_policy("test_channel".to_string(), 0, true);
+        let burn_hash = Base64VecU8(env::sha256(b"third-secret"));
+        contract.nft_mint_pending("test_channel".to_string(), burn_hash.clone(), None);
+        contract.expire_pending_mint(burn_hash);
+        assert_eq!(contract.get_channel_info("test_channel".to_string()).unwrap().total_supply, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Proof required for minting")]
+    fn test_recycled_pending_mint_requires_proof() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(2))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_mint_price("test_channel".to_string(), NearToken::from_near(1));
+        contract.set_pending_mint_policy("test_channel".to_string(), 100, false);
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(1));
+        let secret_hash = Base64VecU8(env::sha256(b"first-secret"));
+        contract.nft_mint_pending("test_channel".to_string(), secret_hash.clone(), None);
+        testing_env!(context
+            .block_timestamp(100)
+            .build());
+        contract.expire_pending_mint(secret_hash);
+
+        // Recycled tokens are gated like fresh mints
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        contract.nft_mint_pending("test_channel".to_string(), Base64VecU8(env::sha256(b"free-secret")), None);
+    }
+
+    #[test]
+    fn test_mint_request_id_dedup() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+
+        let token_id = contract.nft_mint("test_channel".to_string(), None, accounts(2), Some(MintOptions { request_id: Some("req-1".to_string()), ..Default::default() }));
+        let retried = contract.nft_mint("test_channel".to_string(), None, accounts(2), Some(MintOptions { request_id: Some("req-1".to_string()), ..Default::default() }));
+
+        assert_eq!(retried, token_id);
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128(1));
+        assert_eq!(contract.get_mint_request(accounts(1), "req-1".to_string()), Some(token_id));
+        assert_eq!(contract.get_mint_request(accounts(3), "req-1".to_string()), None);
+    }
+
+    #[test]
+    fn test_mint_request_id_expires() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(1));
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), Some(MintOptions { request_id: Some("req-1".to_string()), ..Default::default() }));
+
+        // Past the window the same request id mints again
+        testing_env!(context
+            .block_timestamp(MINT_REQUEST_WINDOW)
+            .build());
+        assert_eq!(contract.get_mint_request(accounts(1), "req-1".to_string()), None);
+        let token_id = contract.nft_mint("test_channel".to_string(), None, accounts(2), Some(MintOptions { request_id: Some("req-1".to_string()), ..Default::default() }));
+        assert_eq!(token_id, "test_channel:2");
+
+        // Later mints drop expired request ids from storage
+        testing_env!(context
+            .block_timestamp(2 * MINT_REQUEST_WINDOW)
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+        assert!(contract.mint_requests.get(&(accounts(1), "req-1".to_string())).is_none());
+        assert_eq!(contract.mint_request_queue_start, contract.mint_request_queue_end);
+    }
+
+    #[test]
+    fn test_commit_reveal_mint() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_commit_reveal("test_channel".to_string(), Some(5));
+
+        let proof: Option<Vec<Vec<u8>>> = None;
+        let commitment = env::sha256(
+            borsh::to_vec(&("test_channel".to_string(), accounts(3), &proof, None::<AccountId>, None::<u64>, None::<String>, "salt".to_string())).unwrap()
+        );
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_millinear(10))
+            .block_height(10)
+            .build());
+        contract.commit_mint(Base64VecU8(commitment.clone()));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .block_height(15)
+            .build());
+        let token_id = contract.reveal_mint("test_channel".to_string(), accounts(3), proof, None, "salt".to_string());
+
+        assert_eq!(token_id, "test_channel:1");
+        assert_eq!(contract.nft_supply_for_owner(accounts(3)), U128(1));
+        assert!(contract.get_mint_commitment(Base64VecU8(commitment)).is_none());
+    }
+
+    #[test]
+    fn test_commit_reveal_requested_number() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            env::sha256(b"test_channel:3"),
+            metadata,
+            None
+        );
+        contract.set_commit_reveal("test_channel".to_string(), Some(5));
+
+        // The committed token number doesn't depend on what else is minted during the delay
+        let proof = Some(vec![]);
+        let commitment = env::sha256(
+            borsh::to_vec(&("test_channel".to_string(), accounts(3), &proof, None::<AccountId>, Some(3u64), None::<String>, "salt".to_string())).unwrap()
+        );
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_millinear(10))
+            .block_height(10)
+            .build());
+        contract.commit_mint(Base64VecU8(commitment));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .block_height(15)
+            .build());
+        let options = MintOptions { token_number: Some(3), ..Default::default() };
+        let token_id = contract.reveal_mint("test_channel".to_string(), accounts(3), proof, Some(options), "salt".to_string());
+        assert_eq!(token_id, "test_channel:3");
+    }
+
+    #[test]
+    #[should_panic(expected = "Commitment is too recent to reveal")]
+    fn test_reveal_mint_too_early() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_commit_reveal("test_channel".to_string(), Some(5));
+
+        let proof: Option<Vec<Vec<u8>>> = None;
+        let commitment = env::sha256(
+            borsh::to_vec(&("test_channel".to_string(), accounts(3), &proof, None::<AccountId>, None::<u64>, None::<String>, "salt".to_string())).unwrap()
+        );
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_near(1))
+            .block_height(10)
+            .build());
+        contract.commit_mint(Base64VecU8(commitment));
+        contract.reveal_mint("test_channel".to_string(), accounts(3), proof, None, "salt".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel requires commit-reveal minting")]
+    fn test_pending_mint_requires_commit_reveal() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_commit_reveal("test_channel".to_string(), Some(5));
+
+        // A proof seen in the mempool cannot be replayed through another mint method
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.nft_mint_pending(
+            "test_channel".to_string(),
+            Base64VecU8(env::sha256(b"secret")),
+            Some(vec![vec![4, 5, 6]])
+        );
+    }
+
+    #[test]
+    fn test_random_rarity_mint() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            Some(vec![
+                RarityTierConfig { name: "common".to_string(), weight: 3, supply: 3 },
+                RarityTierConfig { name: "rare".to_string(), weight: 1, supply: 1 },
+            ])
+        );
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(1));
+
+        let mut token_ids: Vec<TokenId> = (0..4)
+            .map(|_| contract.nft_mint("test_channel".to_string(), None, accounts(2), None))
+            .collect();
+        token_ids.sort();
+
+        assert_eq!(token_ids, vec!["test_channel:1", "test_channel:2", "test_channel:3", "test_channel:4"]);
+        assert!(contract.get_rarity_tiers("test_channel".to_string()).iter().all(|tier| tier.remaining == 0));
+        assert_eq!(contract.get_channel_info("test_channel".to_string()).unwrap().total_possible, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel is sold out")]
+    fn test_random_mint_sold_out() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            Some(vec![RarityTierConfig { name: "legendary".to_string(), weight: 1, supply: 1 }])
+        );
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(1));
+
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Receiver already minted from this channel")]
+    fn test_random_mint_proof_used_once() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        // A single-leaf tree proving `accounts(2)`
+        contract.create_channel(
+            "test_channel".to_string(),
+            env::sha256(format!("test_channel:{}", accounts(2)).as_bytes()),
+            metadata,
+            Some(vec![RarityTierConfig { name: "common".to_string(), weight: 1, supply: 10 }])
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .build());
+        let token_id = contract.nft_mint("test_channel".to_string(), Some(vec![]), accounts(2), None);
+        assert_eq!(contract.get_token_tier(token_id), Some("common".to_string()));
+        contract.nft_mint("test_channel".to_string(), Some(vec![]), accounts(2), None);
+    }
+
+    #[test]
+    fn test_mystery_drop_reveal() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        let final_metadata = ChannelMetadata {
+            title_template: "Revealed #{}".to_string(),
+            description_template: "Revealed".to_string(),
+            media: "https://example.com/final.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/final".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata.clone(),
+            Some(vec![RarityTierConfig { name: "common".to_string(), weight: 1, supply: 2 }])
+        );
+        let provenance_hash = Base64VecU8(env::sha256(borsh::to_vec(&final_metadata).unwrap()));
+        contract.set_mystery_drop("test_channel".to_string(), metadata, provenance_hash);
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(1));
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+
+        let token = contract.nft_token("test_channel:1".to_string()).unwrap();
+        assert_eq!(token.metadata.reference, "https://example.com/ref");
+
+        let reveal = contract.reveal_channel("test_channel".to_string(), final_metadata);
+        assert_eq!(reveal.size, 2);
+        let token = contract.nft_token("test_channel:1".to_string()).unwrap();
+        assert_eq!(token.metadata.title, "Revealed #1");
+        assert_eq!(
+            token.metadata.reference,
+            format!("https://example.com/final/{}", reveal.offset % 2 + 1)
+        );
+        assert!(contract.get_metadata_update_job("test_channel".to_string()).is_none());
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains("\"nft_metadata_update\"") && log.contains("[\"test_channel:1\"]")));
+    }
+
+    #[test]
+    #[should_panic(expected = "Metadata does not match the provenance hash")]
+    fn test_reveal_wrong_metadata() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata.clone(),
+            None
+        );
+        contract.set_mystery_drop("test_channel".to_string(), metadata.clone(), Base64VecU8(vec![0; 32]));
+        contract.reveal_channel("test_channel".to_string(), metadata);
+    }
+
+    #[test]
+    fn test_airdrop() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+
+        let receivers = vec![accounts(2), accounts(3), accounts(4)];
+        assert_eq!(contract.airdrop("test_channel".to_string(), receivers.clone(), None), None);
+        // Resuming past the end mints nothing more
+        assert_eq!(contract.airdrop("test_channel".to_string(), receivers, Some(3)), None);
+
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128(1));
+        assert_eq!(contract.nft_supply_for_owner(accounts(3)), U128(1));
+        assert_eq!(contract.nft_supply_for_owner(accounts(4)), U128(1));
+        assert_eq!(contract.get_channel_info("test_channel".to_string()).unwrap().total_supply, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner or an admin can airdrop")]
+    fn test_airdrop_not_owner() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.airdrop("test_channel".to_string(), vec![accounts(3)], None);
+    }
+
+    #[test]
+    fn test_snapshot_airdrop_per_holder() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata.clone(),
+            None
+        );
+        contract.create_channel(
+            "reward_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.airdrop("test_channel".to_string(), vec![accounts(2), accounts(2), accounts(3)], None);
+        contract.pause_channel("test_channel".to_string());
+
+        let job_id = contract.start_snapshot_airdrop(
+            "test_channel".to_string(),
+            "reward_channel".to_string(),
+            SnapshotReward::PerHolder,
+            2
+        );
+        assert!(contract.run_snapshot_airdrop(job_id));
+
+        let job = contract.get_snapshot_airdrop(job_id).unwrap();
+        assert_eq!((job.cursor, job.total, job.rewarded, job.minted), (3, 3, 2, 4));
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128(4));
+        assert_eq!(contract.nft_supply_for_owner(accounts(3)), U128(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Pause the source channel before snapshotting it")]
+    fn test_snapshot_airdrop_unpaused_source() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata.clone(),
+            None
+        );
+        contract.create_channel(
+            "reward_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.start_snapshot_airdrop(
+            "test_channel".to_string(),
+            "reward_channel".to_string(),
+            SnapshotReward::PerHolder,
+            1
+        );
+    }
+
+    #[test]
+    fn test_snapshot_airdrop_per_token() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata.clone(),
+            None
+        );
+        contract.create_channel(
+            "reward_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.airdrop("test_channel".to_string(), vec![accounts(2), accounts(2), accounts(3)], None);
+
+        // Burned tokens are skipped
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.nft_burn("test_channel:3".to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.pause_channel("test_channel".to_string());
+        let job_id = contract.start_snapshot_airdrop(
+            "test_channel".to_string(),
+            "reward_channel".to_string(),
+            SnapshotReward::PerToken,
+            1
+        );
+        assert!(contract.run_snapshot_airdrop(job_id));
+
+        assert_eq!(contract.get_snapshot_airdrop(job_id).unwrap().minted, 2);
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128(4));
+        assert_eq!(contract.nft_supply_for_owner(accounts(3)), U128(0));
+    }
+
+    #[test]
+    fn test_reserved_token_range() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.reserve_token_range("test_channel".to_string(), 1, 2);
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(1));
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(3));
+
+        // Sequential mints skip the reserved range and numbers taken by request
+        assert_eq!(contract.nft_mint("test_channel".to_string(), None, accounts(2), None), "test_channel:3");
+        assert_eq!(contract.nft_mint("test_channel".to_string(), None, accounts(2), Some(MintOptions { token_number: Some(1), ..Default::default() })), "test_channel:1");
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .build());
+        assert_eq!(contract.nft_mint("test_channel".to_string(), None, accounts(2), Some(MintOptions { token_number: Some(4), ..Default::default() })), "test_channel:4");
+        assert_eq!(contract.nft_mint("test_channel".to_string(), None, accounts(2), None), "test_channel:5");
+    }
+
+    #[test]
+    #[should_panic(expected = "Token number is reserved for the channel owner")]
+    fn test_mint_reserved_number_not_owner() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.reserve_token_range("test_channel".to_string(), 1, 50);
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(3));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(3), Some(MintOptions { token_number: Some(7), ..Default::default() }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Proof required for minting")]
+    fn test_first_public_mint_after_reserved_range_requires_proof() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.reserve_token_range("test_channel".to_string(), 1, 50);
+
+        // Token 51 is the first sequential mint but only token 1 is free of a proof
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+    }
+
+    #[test]
+    fn test_channel_tier_mint() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_channel_tier("test_channel".to_string(), ChannelTierConfig {
+            name: "vip".to_string(),
+            // A single-leaf tree, the root is the leaf's hash and the proof is empty
+            merkle_root: env::sha256(b"test_channel:1"),
+            media: "https://example.com/vip.jpg".to_string(),
+            animation_url: Some("https://example.com/vip.mp4".to_string()),
+            supply: 5,
+            price: NearToken::from_millinear(100),
+        });
+
+        let token_id = contract.nft_mint("test_channel".to_string(), Some(vec![]), accounts(2), Some(MintOptions { tier: Some("vip".to_string()), ..Default::default() }));
+        let token = contract.nft_token(token_id.clone()).unwrap();
+        assert_eq!(token.metadata.media, "https://example.com/vip.jpg");
+        assert_eq!(token.metadata.animation_url, Some("https://example.com/vip.mp4".to_string()));
+        assert_eq!(contract.get_token_tier(token_id), Some("vip".to_string()));
+        assert_eq!(contract.get_channel_tiers("test_channel".to_string())[0].minted, 1);
+        assert_eq!(contract.get_channel_proceeds("test_channel".to_string()), NearToken::from_millinear(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "Proof required for minting")]
+    fn test_channel_tier_first_mint_requires_proof() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_channel_tier("test_channel".to_string(), ChannelTierConfig {
+            name: "vip".to_string(),
+            merkle_root: vec![4, 5, 6],
+            media: "https://example.com/vip.jpg".to_string(),
+            animation_url: None,
+            supply: 5,
+            price: NearToken::from_yoctonear(0),
+        });
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), Some(MintOptions { tier: Some("vip".to_string()), ..Default::default() }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Tier is sold out")]
+    fn test_channel_tier_sold_out() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
         );
+        contract.set_channel_tier("test_channel".to_string(), ChannelTierConfig {
+            name: "vip".to_string(),
+            merkle_root: vec![4, 5, 6],
+            media: "https://example.com/vip.jpg".to_string(),
+            animation_url: None,
+            supply: 1,
+            price: NearToken::from_yoctonear(0),
+        });
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(1));
 
-        // Prevent transferring to the same account
-        assert_ne!(
-            &sender_id, &receiver_id,
-            "The token owner and the receiver should be different"
-        );
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), Some(MintOptions { tier: Some("vip".to_string()), ..Default::default() }));
+        contract.nft_mint("test_channel".to_string(), None, accounts(2), Some(MintOptions { tier: Some("vip".to_string()), ..Default::default() }));
+    }
 
-        // Remove token from sender
-        sender_tokens.remove(&token_id);
-        
-        // Update or remove sender's token set
-        if sender_tokens.is_empty() {
-            self.owners.remove(&sender_id);
-        } else {
-            self.owners.insert(&sender_id, &sender_tokens);
-        }
+    #[test]
+    #[should_panic(expected = "Channel Merkle root is frozen")]
+    fn test_add_channel_tier_root_frozen() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
 
-        // Add token to receiver
-        let mut receiver_tokens = self.owners
-            .get(&receiver_id)
-            .unwrap_or_else(|| UnorderedSet::new(StorageKey::OwnerTokens { 
-                account_id: receiver_id.clone() 
-            }));
-        
-        receiver_tokens.insert(&token_id);
-        self.owners.insert(&receiver_id, &receiver_tokens);
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.freeze_merkle_root("test_channel".to_string());
 
-        // Log the transfer with memo if provided
-        let memo_str = memo.unwrap_or_default();
-        env::log_str(&format!(
-            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"nft_transfer\",\"data\":[{{\"authorized_id\":null,\"old_owner_id\":\"{}\",\"new_owner_id\":\"{}\",\"token_ids\":[\"{}\"],\"memo\":\"{}\"}}]}}",
-            sender_id,
-            receiver_id,
-            token_id,
-            memo_str
-        ));
+        contract.set_channel_tier("test_channel".to_string(), ChannelTierConfig {
+            name: "vip".to_string(),
+            merkle_root: vec![4, 5, 6],
+            media: "https://example.com/vip.jpg".to_string(),
+            animation_url: None,
+            supply: 1,
+            price: NearToken::from_yoctonear(0),
+        });
     }
 
-    #[payable]
-    pub fn nft_burn(&mut self, token_id: TokenId) {
-        let owner_id = env::predecessor_account_id();
-        
-        // Verify ownership
-        let mut owner_tokens = self.owners.get(&owner_id).expect("Owner has no tokens");
-        assert!(owner_tokens.contains(&token_id), "Sender does not own this token");
-
-        // Remove token from owner
-        owner_tokens.remove(&token_id);
-        if owner_tokens.is_empty() {
-            self.owners.remove(&owner_id);
-        } else {
-            self.owners.insert(&owner_id, &owner_tokens);
-        }
-
-        // Remove from minted_tokens
-        self.minted_tokens.remove(&token_id);
+    #[test]
+    #[should_panic(expected = "Channel metadata is frozen")]
+    fn test_add_channel_tier_metadata_frozen() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
 
-        // Update channel data
-        let (channel_id, _) = token_id.split_once(':').unwrap();
-        let mut channel = self.channels.get(&channel_id.to_string()).expect("Channel not found");
-        channel.total_supply -= 1;
-        self.channels.insert(&channel_id.to_string(), &channel);
+        let metadata = ChannelMetadata {
+            title_template: "Test Channel #{}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.freeze_channel_metadata("test_channel".to_string());
 
-        // Emit burn event
-        env::log_str(&format!(
-            "EVENT_JSON:{{\"standard\":\"nep171\",\"version\":\"1.0.0\",\"event\":\"nft_burn\",\"data\":[{{\"owner_id\":\"{}\",\"token_ids\":[\"{}\"]}}]}}",
-            owner_id, token_id
-        ));
+        contract.set_channel_tier("test_channel".to_string(), ChannelTierConfig {
+            name: "vip".to_string(),
+            merkle_root: vec![4, 5, 6],
+            media: "https://example.com/vip.jpg".to_string(),
+            animation_url: None,
+            supply: 1,
+            price: NearToken::from_yoctonear(0),
+        });
     }
 
-    pub fn nft_tokens_for_owner(
-        &self,
-        account_id: AccountId,
-        from_index: Option<U128>,
-        limit: Option<u64>
-    ) -> Vec<JsonToken> {
-        // Get the set of tokens for the given account, or create an empty set if none exists
-        let tokens = self.owners.get(&account_id)
-            .unwrap_or_else(|| UnorderedSet::new(StorageKey::OwnerTokens { 
-                account_id: account_id.clone() 
-            }));
-        
-        // Convert from_index from U128 to usize
-        let start = u128::from(from_index.unwrap_or(U128(0))) as usize;
-        let limit = limit.unwrap_or(50) as usize;  // Default limit of 50 tokens
+    #[test]
+    fn test_metadata_templates() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .block_timestamp(1_700_000_000_000_000_000)
+            .build());
+        let mut contract = Contract::new(accounts(1));
 
-        // Get the tokens with pagination
-        tokens.iter()
-            .skip(start)
-            .take(limit)
-            .filter_map(|token_id| self.nft_token(token_id.clone()))
-            .collect()
-    }
+        let metadata = ChannelMetadata {
+            title_template: "Card {number:03} of {max_supply}".to_string(),
+            description_template: "{channel} {tier} card minted {minted_date} for {owner}".to_string(),
+            media: "https://example.com/{item}.png".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref/{number}.json".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
+        contract.set_channel_tier("test_channel".to_string(), ChannelTierConfig {
+            name: "gold".to_string(),
+            merkle_root: env::sha256(b"test_channel:1"),
+            media: "https://example.com/gold/{number:02}.png".to_string(),
+            animation_url: None,
+            supply: 100,
+            price: NearToken::from_yoctonear(0),
+        });
+        let token_id = contract.nft_mint("test_channel".to_string(), Some(vec![]), accounts(2), Some(MintOptions { tier: Some("gold".to_string()), ..Default::default() }));
 
-    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> near_sdk::json_types::U128 {
-        near_sdk::json_types::U128(
-            self.owners
-                .get(&account_id)
-                .map(|tokens| tokens.len() as u128)
-                .unwrap_or(0)
-        )
+        let token = contract.nft_token(token_id).unwrap();
+        assert_eq!(token.metadata.title, "Card 001 of 100");
+        assert_eq!(token.metadata.description, "test_channel gold card minted 2023-11-14 for charlie");
+        assert_eq!(token.metadata.media, "https://example.com/gold/01.png");
+        assert_eq!(token.metadata.reference, "https://example.com/ref/1.json");
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::{testing_env, AccountId};
 
-    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
-        let mut builder = VMContextBuilder::new();
-        builder
-            .current_account_id(accounts(0))
-            .signer_account_id(predecessor_account_id.clone())
-            .predecessor_account_id(predecessor_account_id);
-        builder
+    #[test]
+    fn test_render_template_unclosed_brace() {
+        let context = TemplateContext {
+            number: 7,
+            item: 7,
+            channel: "test_channel",
+            tier: None,
+            max_supply: None,
+            minted_at: None,
+            owner: None,
+            state: None,
+        };
+        assert_eq!(render_template("abc {def", &context), "abc {def");
+        assert_eq!(render_template("#{number} {def", &context), "#7 {def");
     }
 
     #[test]
-    fn test_new() {
+    #[should_panic(expected = "Unknown template placeholder {rank}")]
+    fn test_invalid_metadata_template() {
         let context = get_context(accounts(1));
         testing_env!(context.build());
-        let contract = Contract::new(accounts(1));
-        assert_eq!(contract.owner_id, accounts(1));
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Card {rank}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
+            "test_channel".to_string(),
+            vec![1, 2, 3],
+            metadata,
+            None
+        );
     }
 
     #[test]
-    fn test_create_channel() {
+    fn test_token_metadata_override() {
         let mut context = get_context(accounts(1));
-        testing_env!(context.build());
+        testing_env!(context.attached_deposit(NearToken::from_near(1)).build());
         let mut contract = Contract::new(accounts(1));
 
         let metadata = ChannelMetadata {
-            title_template: "Test Channel #{}".to_string(),
+            title_template: "Card {number}".to_string(),
             description_template: "Test Description".to_string(),
-            media: "https://example.com/image.jpg".to_string(),
+            media: "https://example.com/{number}.png".to_string(),
             animation_url: None,
             reference: "https://example.com/ref".to_string(),
             reference_hash: None,
         };
-
         contract.create_channel(
             "test_channel".to_string(),
-            vec![1, 2, 3],  // Example merkle root
-            metadata.clone()
+            vec![1, 2, 3],
+            metadata,
+            None
         );
+        let token_id = contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
 
-        let channel = contract.get_channel_info("test_channel".to_string()).unwrap();
-        assert_eq!(channel.total_supply, 0);
-        assert_eq!(channel.next_token_number, 1);
-        assert_eq!(channel.metadata.title_template, "Test Channel #{}");
+        contract.set_token_metadata_override(token_id.clone(), TokenMetadataOverride {
+            media: Some("https://example.com/one-off.png".to_string()),
+            ..Default::default()
+        });
+        let token = contract.nft_token(token_id.clone()).unwrap();
+        assert_eq!(token.metadata.title, "Card 1");
+        assert_eq!(token.metadata.media, "https://example.com/one-off.png");
+
+        contract.remove_token_metadata_override(token_id.clone());
+        assert!(contract.get_token_metadata_override(token_id.clone()).is_none());
+        assert_eq!(contract.nft_token(token_id).unwrap().metadata.media, "https://example.com/1.png");
     }
 
     #[test]
-    fn test_mint_and_transfer() {
+    #[should_panic(expected = "Channel metadata is frozen")]
+    fn test_token_metadata_override_frozen() {
         let mut context = get_context(accounts(1));
-        testing_env!(context.build());
+        testing_env!(context.attached_deposit(NearToken::from_near(1)).build());
         let mut contract = Contract::new(accounts(1));
 
-        // Create channel
         let metadata = ChannelMetadata {
-            title_template: "Test Channel #{}".to_string(),
+            title_template: "Test Title".to_string(),
             description_template: "Test Description".to_string(),
             media: "https://example.com/image.jpg".to_string(),
             animation_url: None,
             reference: "https://example.com/ref".to_string(),
             reference_hash: None,
         };
-
         contract.create_channel(
             "test_channel".to_string(),
             vec![1, 2, 3],
-            metadata
+            metadata,
+            None
         );
+        let token_id = contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+        contract.freeze_channel_metadata("test_channel".to_string());
 
-        // Mint token
-        let (token_id, _) = contract.nft_mint(
+        contract.set_token_metadata_override(token_id, TokenMetadataOverride {
+            title: Some("One-off".to_string()),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_evolving_token_state() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(NearToken::from_near(1)).build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Loyalty Card {number} - Level {level}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/{attribute.stage}.png".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
             "test_channel".to_string(),
-            None,  // First token doesn't need proof
-            accounts(2)
+            vec![1, 2, 3],
+            metadata,
+            None
         );
+        let token_id = contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+        let token = contract.nft_token(token_id.clone()).unwrap();
+        assert_eq!(token.metadata.title, "Loyalty Card 1 - Level 0");
+        assert!(token.metadata.extra.is_none());
 
-        // Check ownership
-        let owner_tokens = contract.nft_tokens_for_owner(accounts(2), None, None);
-        assert_eq!(owner_tokens.len(), 1);
-        assert_eq!(owner_tokens[0].token_id, token_id);
-
-        // Transfer token
-        testing_env!(context
-            .predecessor_account_id(accounts(2))
-            .build());
-        contract.nft_transfer(token_id.clone(), accounts(3));
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::StateUpdater, accounts(3));
+        testing_env!(get_context(accounts(3)).build());
+        contract.update_token_state(token_id.clone(), Some(2), Some(vec![
+            ("stage".to_string(), Some("silver".to_string())),
+            ("visits".to_string(), Some("12".to_string())),
+        ]));
+        contract.update_token_state(token_id.clone(), None, Some(vec![("visits".to_string(), None)]));
 
-        // Check new ownership
-        let new_owner_tokens = contract.nft_tokens_for_owner(accounts(3), None, None);
-        assert_eq!(new_owner_tokens.len(), 1);
-        assert_eq!(new_owner_tokens[0].token_id, token_id);
+        let token = contract.nft_token(token_id).unwrap();
+        assert_eq!(token.metadata.title, "Loyalty Card 1 - Level 2");
+        assert_eq!(token.metadata.media, "https://example.com/silver.png");
+        assert_eq!(token.metadata.extra.unwrap(), "{\"level\":2,\"attributes\":{\"stage\":\"silver\"}}");
     }
 
     #[test]
-    fn test_burn() {
+    #[should_panic(expected = "Only the owner, an admin, a channel manager or a state updater can update token state")]
+    fn test_update_token_state_not_updater() {
         let mut context = get_context(accounts(1));
-        testing_env!(context.build());
+        testing_env!(context.attached_deposit(NearToken::from_near(1)).build());
         let mut contract = Contract::new(accounts(1));
 
-        // Create channel and mint token
         let metadata = ChannelMetadata {
-            title_template: "Test Channel #{}".to_string(),
+            title_template: "Test Title".to_string(),
             description_template: "Test Description".to_string(),
             media: "https://example.com/image.jpg".to_string(),
             animation_url: None,
             reference: "https://example.com/ref".to_string(),
             reference_hash: None,
         };
-
         contract.create_channel(
             "test_channel".to_string(),
             vec![1, 2, 3],
-            metadata
+            metadata,
+            None
         );
+        let token_id = contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+
+        testing_env!(get_context(accounts(2)).build());
+        contract.update_token_state(token_id, Some(5), None);
+    }
 
-        let (token_id, _) = contract.nft_mint(
+    #[test]
+    #[should_panic(expected = "Channel metadata is frozen")]
+    fn test_update_token_state_frozen() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(NearToken::from_near(1)).build());
+        let mut contract = Contract::new(accounts(1));
+
+        let metadata = ChannelMetadata {
+            title_template: "Test Title".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/image.jpg".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.create_channel(
             "test_channel".to_string(),
-            None,
-            accounts(2)
+            vec![1, 2, 3],
+            metadata,
+            None
         );
+        let token_id = contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+        contract.freeze_channel_metadata("test_channel".to_string());
 
-        // Burn token
-        testing_env!(context
-            .predecessor_account_id(accounts(2))
-            .build());
-        contract.nft_burn(token_id.clone());
-
-        // Verify token is burned
-        assert!(!contract.is_minted(token_id));
-        let owner_tokens = contract.nft_tokens_for_owner(accounts(2), None, None);
-        assert_eq!(owner_tokens.len(), 0);
+        contract.update_token_state(token_id, Some(5), None);
     }
 
     #[test]
-    #[should_panic(expected = "Only the owner can create channels")]
-    fn test_create_channel_not_owner() {
-        let mut context = get_context(accounts(2));
-        testing_env!(context.build());
+    fn test_template_change_announces_metadata_update() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(NearToken::from_near(1)).build());
         let mut contract = Contract::new(accounts(1));
 
         let metadata = ChannelMetadata {
-            title_template: "Test Channel #{}".to_string(),
+            title_template: "Test Title".to_string(),
             description_template: "Test Description".to_string(),
             media: "https://example.com/image.jpg".to_string(),
             animation_url: None,
             reference: "https://example.com/ref".to_string(),
             reference_hash: None,
         };
-
         contract.create_channel(
             "test_channel".to_string(),
             vec![1, 2, 3],
-            metadata
+            metadata,
+            None
         );
+        contract.grant_channel_role("test_channel".to_string(), ChannelRole::Minter, accounts(1));
+        for _ in 0..3 {
+            contract.nft_mint("test_channel".to_string(), None, accounts(2), None);
+        }
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.nft_burn("test_channel:2".to_string());
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+
+        let new_metadata = ChannelMetadata {
+            title_template: "Card {number}".to_string(),
+            description_template: "Test Description".to_string(),
+            media: "https://example.com/{number}.png".to_string(),
+            animation_url: None,
+            reference: "https://example.com/ref".to_string(),
+            reference_hash: None,
+        };
+        contract.update_channel("test_channel".to_string(), None, Some(new_metadata));
+
+        // Three tokens fit in the first page, so the job is already done and skipped the burned one
+        assert!(contract.get_metadata_update_job("test_channel".to_string()).is_none());
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains("[\"test_channel:1\",\"test_channel:3\"]")));
+        assert_eq!(contract.nft_token("test_channel:3".to_string()).unwrap().metadata.title, "Card 3");
     }
 
     #[test]
     #[should_panic(expected = "Sender does not own this token")]
     fn test_transfer_not_owner() {
         let mut context = get_context(accounts(1));
-        testing_env!(context.build());
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .build());
         let mut contract = Contract::new(accounts(1));
 
         // Create channel and mint token
@@ -628,19 +6491,22 @@ mod tests {
         contract.create_channel(
             "test_channel".to_string(),
             vec![1, 2, 3],
-            metadata
+            metadata,
+            None
         );
 
-        let (token_id, _) = contract.nft_mint(
+        let token_id = contract.nft_mint(
             "test_channel".to_string(),
             None,
-            accounts(2)
+            accounts(2),
+            None
         );
 
         // Try to transfer token from wrong account
         testing_env!(context
             .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(1))
             .build());
-        contract.nft_transfer(token_id, accounts(4));
+        contract.nft_transfer(accounts(4), token_id, None, None);
     }
 }
\ No newline at end of file